@@ -0,0 +1,58 @@
+use good_lp::{
+    clarabel, solvers::clarabel::ClarabelProblem, variable::UnsolvedProblem, ResolutionError,
+    SolverModel,
+};
+#[cfg(feature = "microlp")]
+use good_lp::{microlp, solvers::microlp::MicroLpProblem};
+use serde::{Deserialize, Serialize};
+
+use super::{config::config_solver, SolverSettings};
+
+/// 可选的线性规划求解后端
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum SolverBackend {
+    /// 内点法求解器clarabel
+    #[default]
+    Clarabel,
+    /// 纯Rust的单纯形法求解器microlp
+    #[cfg(feature = "microlp")]
+    Microlp,
+}
+
+/// 线性规划求解后端
+///
+/// 约束的构建与求解器无关，后端只负责为待求解的问题选择求解器并应用设置
+pub trait Backend {
+    type Model: SolverModel<Error = ResolutionError>;
+
+    fn model(problem: UnsolvedProblem, settings: &SolverSettings) -> Self::Model;
+}
+
+pub struct Clarabel;
+
+impl Backend for Clarabel {
+    type Model = ClarabelProblem;
+
+    fn model(problem: UnsolvedProblem, settings: &SolverSettings) -> Self::Model {
+        let mut clarabel_problem = problem.using(clarabel);
+        config_solver(&mut clarabel_problem, settings);
+        clarabel_problem
+    }
+}
+
+/// 求解器设置中的精度和迭代次数只对clarabel有效
+#[cfg(feature = "microlp")]
+pub struct Microlp;
+
+#[cfg(feature = "microlp")]
+impl Backend for Microlp {
+    type Model = MicroLpProblem;
+
+    fn model(problem: UnsolvedProblem, _settings: &SolverSettings) -> Self::Model {
+        problem.using(microlp)
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use super::{Problem, Solution, THRESHOLD};
+use crate::dsp::item::{Resource, ResourceType};
+
+/// 一个公式对某种资源的产出或消耗
+#[derive(Clone, Copy, Debug)]
+pub struct Flow {
+    /// 公式在解中的下标
+    pub solution: usize,
+    /// 速率，单位为每帧
+    pub rate: f64,
+}
+
+/// 一种资源的物料平衡，单位为每帧
+#[derive(Clone, Debug)]
+pub struct ItemBalance {
+    pub resource_type: ResourceType,
+    /// 所有公式的总产量
+    pub production: f64,
+    /// 所有公式的总消耗量
+    pub consumption: f64,
+    /// 需求量
+    pub demand: f64,
+    /// 产出该资源的公式
+    pub producers: Vec<Flow>,
+    /// 消耗该资源的公式
+    pub consumers: Vec<Flow>,
+}
+
+/// 资源流动的起点或终点
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    /// 解中第几个公式
+    Recipe(usize),
+    /// 外部输入，即产量不足的部分
+    Import,
+    /// 需求
+    Demand,
+    /// 剩余的副产物
+    Surplus,
+}
+
+/// 一个端点在某种资源的总流量中所占的份额
+#[derive(Clone, Copy, Debug)]
+pub struct Share {
+    pub endpoint: Endpoint,
+    /// 速率，单位为每帧
+    pub rate: f64,
+    /// 占总流量的比例
+    pub share: f64,
+}
+
+/// 某种资源从一个来源流向一个去向的速率，单位为每帧
+#[derive(Clone, Copy, Debug)]
+pub struct ItemFlow {
+    pub from: Endpoint,
+    pub to: Endpoint,
+    pub rate: f64,
+}
+
+impl ItemBalance {
+    /// 扣除消耗和需求后的剩余量，为负时由外部输入
+    #[must_use]
+    pub fn surplus(&self) -> f64 {
+        self.production - self.consumption - self.demand
+    }
+
+    /// 外部输入量，即产量不足以覆盖消耗和需求的部分
+    #[must_use]
+    pub fn import(&self) -> f64 {
+        (-self.surplus()).max(0.0)
+    }
+
+    // 来源和去向的总流量，两者相等
+    fn total(&self) -> f64 {
+        self.production + self.import()
+    }
+
+    /// 每个来源的速率和占比，包括生产公式和外部输入
+    #[must_use]
+    pub fn sources(&self) -> Vec<Share> {
+        let endpoints = self
+            .producers
+            .iter()
+            .map(|flow| (Endpoint::Recipe(flow.solution), flow.rate))
+            .chain([(Endpoint::Import, self.import())]);
+        self.shares(endpoints)
+    }
+
+    /// 每个去向的速率和占比，包括消耗公式、需求和剩余
+    #[must_use]
+    pub fn sinks(&self) -> Vec<Share> {
+        let endpoints = self
+            .consumers
+            .iter()
+            .map(|flow| (Endpoint::Recipe(flow.solution), flow.rate))
+            .chain([
+                (Endpoint::Demand, self.demand),
+                (Endpoint::Surplus, self.surplus().max(0.0)),
+            ]);
+        self.shares(endpoints)
+    }
+
+    /// 把资源的流动分解为从来源到去向的流量
+    ///
+    /// 假设各来源的产物混合后按比例分给各去向，即每个去向从每个来源获得的量与该来源的份额成正比
+    #[must_use]
+    pub fn flows(&self) -> Vec<ItemFlow> {
+        let sinks = self.sinks();
+        self.sources()
+            .into_iter()
+            .flat_map(|source| {
+                sinks.iter().map(move |sink| ItemFlow {
+                    from: source.endpoint,
+                    to: sink.endpoint,
+                    rate: source.share * sink.rate,
+                })
+            })
+            .collect()
+    }
+
+    // 计算占比，忽略速率可以忽略的端点
+    fn shares(&self, endpoints: impl Iterator<Item = (Endpoint, f64)>) -> Vec<Share> {
+        let total = self.total();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        endpoints
+            .filter(|&(_, rate)| rate > THRESHOLD * total)
+            .map(|(endpoint, rate)| Share {
+                endpoint,
+                rate,
+                share: rate / total,
+            })
+            .collect()
+    }
+}
+
+/// 统计解中每种资源的产量、消耗量和相关的公式
+///
+/// 约束只要求净产量不低于需求，副产物会有剩余，原矿的剩余为负
+#[must_use]
+pub fn balance(solutions: &[Solution], needs: &[Resource]) -> Vec<ItemBalance> {
+    let mut consumed_by: HashMap<ResourceType, Vec<Flow>> = HashMap::new();
+    let mut produced_by: HashMap<ResourceType, Vec<Flow>> = HashMap::new();
+    for (index, solution) in solutions.iter().enumerate() {
+        let scale = solution.num / solution.recipe.time;
+        // 处理消耗项
+        for item in &solution.recipe.items {
+            consumed_by
+                .entry(item.resource_type)
+                .or_default()
+                .push(Flow {
+                    solution: index,
+                    rate: scale * item.num,
+                });
+        }
+        // 处理生产项
+        for result in &solution.recipe.results {
+            produced_by
+                .entry(result.resource_type)
+                .or_default()
+                .push(Flow {
+                    solution: index,
+                    rate: scale * result.num,
+                });
+        }
+    }
+
+    let mut resource_types: Vec<_> = consumed_by
+        .keys()
+        .chain(produced_by.keys())
+        .chain(needs.iter().map(|need| &need.resource_type))
+        .copied()
+        .collect();
+    resource_types.sort_by_cached_key(ToString::to_string);
+    resource_types.dedup();
+
+    resource_types
+        .into_iter()
+        .map(|resource_type| {
+            let producers = produced_by.remove(&resource_type).unwrap_or_default();
+            let consumers = consumed_by.remove(&resource_type).unwrap_or_default();
+            ItemBalance {
+                resource_type,
+                production: producers.iter().map(|flow| flow.rate).sum(),
+                consumption: consumers.iter().map(|flow| flow.rate).sum(),
+                demand: needs
+                    .iter()
+                    .filter(|need| need.resource_type == resource_type)
+                    .map(|need| need.num)
+                    .sum(),
+                producers,
+                consumers,
+            }
+        })
+        .collect()
+}
+
+impl Problem {
+    /// 用问题的需求统计解的物料平衡
+    #[must_use]
+    pub fn balance(&self, solutions: &[Solution]) -> Vec<ItemBalance> {
+        balance(solutions, &self.all_needs())
+    }
+}
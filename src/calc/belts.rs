@@ -0,0 +1,71 @@
+use super::Solution;
+use crate::dsp::{
+    belt::BeltSettings,
+    item::{Resource, ResourceType},
+};
+
+/// 一种原料或产物需要的传送带
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct BeltLoad {
+    pub resource_type: ResourceType,
+    /// 速率，单位为每帧
+    pub rate: f64,
+    /// 传送带数量，不取整
+    pub belts: f64,
+}
+
+/// 一条产线的原料和产物分别需要的传送带
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecipeBelts {
+    /// 产线在解中的下标
+    pub solution: usize,
+    pub inputs: Vec<BeltLoad>,
+    pub outputs: Vec<BeltLoad>,
+}
+
+impl RecipeBelts {
+    fn loads(&self) -> impl Iterator<Item = &BeltLoad> {
+        self.inputs.iter().chain(&self.outputs)
+    }
+
+    /// 原料和产物需要的传送带数量之和
+    #[must_use]
+    pub fn total(&self) -> f64 {
+        self.loads().fold(0.0, |sum, load| sum + load.belts)
+    }
+
+    /// 每种原料和产物分别向上取整后的传送带数量之和，即实际需要铺设的数量
+    #[must_use]
+    pub fn ceil_total(&self) -> f64 {
+        self.loads().fold(0.0, |sum, load| sum + load.belts.ceil())
+    }
+}
+
+/// 计算每条产线的每种原料和产物需要的传送带，电力等不用传送带运送的资源不计入
+#[must_use]
+pub fn belt_report(solutions: &[Solution], settings: &BeltSettings) -> Vec<RecipeBelts> {
+    let capacity = settings.capacity();
+    let loads = |solution: &Solution, resources: &[Resource]| {
+        resources
+            .iter()
+            .filter(|resource| matches!(resource.resource_type, ResourceType::Direct(_)))
+            .map(|resource| {
+                let rate = solution.num * resource.num / solution.recipe.time;
+                BeltLoad {
+                    resource_type: resource.resource_type,
+                    rate,
+                    belts: rate / capacity,
+                }
+            })
+            .collect()
+    };
+    solutions
+        .iter()
+        .enumerate()
+        .map(|(index, solution)| RecipeBelts {
+            solution: index,
+            inputs: loads(solution, &solution.recipe.items),
+            outputs: loads(solution, &solution.recipe.results),
+        })
+        .collect()
+}
@@ -0,0 +1,51 @@
+use super::Solution;
+use crate::dsp::building::BuildingType;
+
+/// 一种建筑的统计
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct BuildingStats {
+    pub building_type: BuildingType,
+    /// 建筑数量的总和
+    pub count: f64,
+    /// 每条产线分别向上取整后的建筑数量总和，即实际需要摆放的数量
+    pub ceil_count: f64,
+    /// 总功率，单位为MJ每帧，按建筑数量的总和计算
+    pub power: f64,
+    /// 总占地，按建筑数量的总和计算
+    pub area: f64,
+    /// 总卡顿，按建筑数量的总和计算
+    pub lag: f64,
+}
+
+/// 按建筑类型统计解中的建筑，按首次出现的顺序排列
+#[must_use]
+pub fn building_stats(solutions: &[Solution]) -> Vec<BuildingStats> {
+    let mut stats: Vec<BuildingStats> = Vec::new();
+    for solution in solutions {
+        let building_type = solution.recipe.info.building_type;
+        let index = stats
+            .iter()
+            .position(|stat| stat.building_type == building_type)
+            .unwrap_or_else(|| {
+                stats.push(BuildingStats {
+                    building_type,
+                    count: 0.0,
+                    ceil_count: 0.0,
+                    power: 0.0,
+                    area: 0.0,
+                    lag: 0.0,
+                });
+                stats.len() - 1
+            });
+        if let Some(stat) = stats.get_mut(index) {
+            stat.count += solution.num;
+            stat.ceil_count += solution.num.ceil();
+        }
+    }
+    for stat in &mut stats {
+        stat.power = stat.count * stat.building_type.power();
+        stat.area = stat.count * stat.building_type.area();
+        stat.lag = stat.count * stat.building_type.lag();
+    }
+    stats
+}
@@ -1,15 +1,56 @@
 use good_lp::solvers::clarabel::ClarabelProblem;
+use serde::{Deserialize, Serialize};
 
-/// 求解精度配置
-pub fn config_solver(problem: &mut ClarabelProblem) {
+use super::SolverBackend;
+
+/// 求解器设置
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverSettings {
+    /// 线性规划求解后端
+    pub backend: SolverBackend,
+    /// 是否输出求解过程
+    pub verbose: bool,
+    /// 求解精度，同时用于对偶间隙、可行性和不可行性判定
+    pub tolerance: f64,
+    /// 最大迭代次数
+    pub max_iter: u32,
+    /// 是否把内点法的解移动到可行域的顶点上，使每个自由度最多使用一个公式
+    pub crossover: bool,
+    /// 字典序多目标优化中，前一阶段的最优值允许放宽的相对比例
+    pub stage_tolerance: f64,
+    /// 整数求解时分支定界最多求解的线性规划数量
+    pub max_nodes: usize,
+    /// 检查解的物料平衡时，缺口相对于资源总流量的容差
+    pub verify_tolerance: f64,
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        Self {
+            backend: SolverBackend::default(),
+            verbose: true,
+            tolerance: f64::EPSILON,
+            max_iter: u32::MAX,
+            crossover: true,
+            stage_tolerance: 1e-6,
+            max_nodes: 1000,
+            verify_tolerance: 1e-6,
+        }
+    }
+}
+
+/// clarabel的求解精度配置
+pub fn config_solver(problem: &mut ClarabelProblem, settings: &SolverSettings) {
+    let tolerance = settings.tolerance;
     problem
         .settings()
-        .verbose(true) // 启用详细输出
-        .tol_gap_abs(f64::EPSILON)
-        .tol_gap_rel(f64::EPSILON)
-        .tol_feas(f64::EPSILON)
-        .tol_infeas_abs(f64::EPSILON)
-        .tol_infeas_rel(f64::EPSILON)
+        .verbose(settings.verbose) // 启用详细输出
+        .tol_gap_abs(tolerance)
+        .tol_gap_rel(tolerance)
+        .tol_feas(tolerance)
+        .tol_infeas_abs(tolerance)
+        .tol_infeas_rel(tolerance)
         .equilibrate_max_iter(256)
         .equilibrate_min_scaling(1.0)
         .equilibrate_max_scaling(1.0)
@@ -18,5 +59,5 @@ pub fn config_solver(problem: &mut ClarabelProblem) {
         .dynamic_regularization_delta(f64::EPSILON)
         .iterative_refinement_reltol(f64::EPSILON)
         .iterative_refinement_abstol(f64::EPSILON)
-        .max_iter(u32::MAX);
+        .max_iter(settings.max_iter);
 }
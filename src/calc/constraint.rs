@@ -1,6 +1,4 @@
-use good_lp::{
-    constraint::ConstraintReference, solvers::clarabel::ClarabelProblem, Expression, SolverModel,
-};
+use good_lp::{constraint::ConstraintReference, Expression, SolverModel, Variable};
 
 use super::ProcessedRecipes;
 use crate::dsp::item::{Resource, ResourceType};
@@ -10,7 +8,7 @@ use crate::dsp::item::{Resource, ResourceType};
 /// 本质是把公式约束视为需求量为0的需求约束
 pub fn constraint_recipes(
     processed: &ProcessedRecipes,
-    problem: &mut ClarabelProblem,
+    problem: &mut impl SolverModel,
     production_types: &[ResourceType],
 ) -> Vec<ConstraintReference> {
     let needs = production_types
@@ -28,7 +26,7 @@ pub fn constraint_recipes(
 /// 对需求列表中的每一项资源创建一个需求约束，返回相应的约束引用列表
 pub fn constraint_needs(
     processed: &ProcessedRecipes,
-    problem: &mut ClarabelProblem,
+    problem: &mut impl SolverModel,
     needs: &[Resource],
 ) -> Vec<ConstraintReference> {
     needs
@@ -37,18 +35,61 @@ pub fn constraint_needs(
         .collect()
 }
 
+/// 创建所有供给约束
+///
+/// 对供给列表中的每一项资源，消耗量**总和** - 产出量**总和** <= 供给量
+pub fn constraint_supplies(
+    processed: &ProcessedRecipes,
+    problem: &mut impl SolverModel,
+    supplies: &[Resource],
+) -> Vec<ConstraintReference> {
+    supplies
+        .iter()
+        .map(|supply| {
+            problem.add_constraint(net_production(processed, supply.resource_type).geq(-supply.num))
+        })
+        .collect()
+}
+
+/// 创建带松弛变量的需求约束
+///
+/// 松弛变量补足产量缺口，使任何需求都能被满足，用于定位不可行的原因
+pub fn constraint_needs_elastic(
+    processed: &ProcessedRecipes,
+    problem: &mut impl SolverModel,
+    needs: &[Resource],
+    slacks: &[Variable],
+) -> Vec<ConstraintReference> {
+    needs
+        .iter()
+        .zip(slacks)
+        .map(|(need, &slack)| {
+            problem.add_constraint(
+                (net_production(processed, need.resource_type) + slack).geq(need.num),
+            )
+        })
+        .collect()
+}
+
 /// 创建一个需求约束
 ///
 /// 对所有出现的配方，产出量**总和** - 消耗量**总和** >= 需求量
 fn create_constraint(
     processed: &ProcessedRecipes,
-    problem: &mut ClarabelProblem,
+    problem: &mut impl SolverModel,
     need: Resource,
 ) -> ConstraintReference {
+    problem.add_constraint(net_production(processed, need.resource_type).geq(need.num))
+}
+
+/// 资源的净产量表达式
+///
+/// 对所有出现的配方，产出量**总和** - 消耗量**总和**
+pub fn net_production(processed: &ProcessedRecipes, resource_type: ResourceType) -> Expression {
     let (consumes, produces) = (
         // 当 get() 返回 None 时，sum() 会默认为 0
-        processed.consumes.get(&need.resource_type),
-        processed.produces.get(&need.resource_type),
+        processed.consumes.get(&resource_type),
+        processed.produces.get(&resource_type),
     );
 
     let items_expr: Expression = consumes
@@ -63,5 +104,5 @@ fn create_constraint(
         .map(|(recipe, rate)| *rate * recipe.variable)
         .sum();
 
-    problem.add_constraint((results_expr - items_expr).geq(need.num))
+    results_expr - items_expr
 }
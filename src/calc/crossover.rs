@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+
+use super::{
+    lexicographic::{Bound, Goal},
+    Problem, RecipeBinding, THRESHOLD, TIE_BREAK,
+};
+use crate::dsp::item::ResourceType;
+
+/// 约束的系数形式：系数与变量乘积的总和 >= 下限
+///
+/// 变量用公式绑定的下标表示
+pub struct Row {
+    pub coefficients: Vec<(usize, f64)>,
+    pub lower: f64,
+}
+
+impl Row {
+    pub(super) fn value(&self, values: &[f64]) -> f64 {
+        self.coefficients
+            .iter()
+            .map(|&(index, coefficient)| coefficient * values.get(index).copied().unwrap_or(0.0))
+            .sum()
+    }
+}
+
+impl Problem {
+    /// 与`solve_lp`相同的约束的系数形式
+    pub(super) fn rows(
+        &self,
+        recipe_extra: &[RecipeBinding],
+        bounds: &[Bound],
+        max_num: Option<f64>,
+    ) -> Vec<Row> {
+        let net = Self::net_coefficients(recipe_extra);
+        let net_row = |resource_type: ResourceType, lower: f64| Row {
+            coefficients: net.get(&resource_type).cloned().unwrap_or_default(),
+            lower,
+        };
+
+        let mut rows = Vec::new();
+        rows.extend(
+            self.balanced_productions()
+                .into_iter()
+                .map(|production| net_row(production, 0.0)),
+        );
+        rows.extend(
+            self.balanced_needs()
+                .into_iter()
+                .map(|need| net_row(need.resource_type, need.num)),
+        );
+        rows.extend(
+            self.remaining_supplies()
+                .into_iter()
+                .map(|supply| net_row(supply.resource_type, -supply.num)),
+        );
+        rows.extend(
+            bounds
+                .iter()
+                .chain(&self.fixed_bounds(recipe_extra))
+                .map(|bound| match bound {
+                    Bound::Cost(weights, max) => Row {
+                        coefficients: weights
+                            .iter()
+                            .enumerate()
+                            .map(|(index, &weight)| (index, -weight))
+                            .collect(),
+                        lower: -max,
+                    },
+                    Bound::Throughput(min) => Row {
+                        coefficients: self.throughput_coefficients(&net),
+                        lower: *min,
+                    },
+                    Bound::Unused(index) => Row {
+                        coefficients: vec![(*index, -1.0)],
+                        lower: 0.0,
+                    },
+                    Bound::AtMost(index, max) => Row {
+                        coefficients: vec![(*index, -1.0)],
+                        lower: -max,
+                    },
+                    Bound::AtLeast(index, min) => Row {
+                        coefficients: vec![(*index, 1.0)],
+                        lower: *min,
+                    },
+                }),
+        );
+        if let Some(max_num) = max_num {
+            rows.extend((0..recipe_extra.len()).map(|index| Row {
+                coefficients: vec![(index, -1.0)],
+                lower: -max_num,
+            }));
+        }
+        rows
+    }
+
+    /// 与`solve_lp`相同的优化目标的系数形式，统一为最小化
+    pub(super) fn objective_coefficients(
+        &self,
+        recipe_extra: &[RecipeBinding],
+        goal: Goal,
+    ) -> Vec<f64> {
+        let mut objective: Vec<_> = recipe_extra.iter().map(|recipe| recipe.weight).collect();
+        if matches!(goal, Goal::MaximizeThroughput) {
+            for weight in &mut objective {
+                *weight *= TIE_BREAK;
+            }
+            let net = Self::net_coefficients(recipe_extra);
+            for (index, coefficient) in self.throughput_coefficients(&net) {
+                if let Some(weight) = objective.get_mut(index) {
+                    *weight -= coefficient;
+                }
+            }
+        }
+        objective
+    }
+
+    // 每种资源的净产量系数，同一公式既消耗又产出时合并
+    fn net_coefficients(
+        recipe_extra: &[RecipeBinding],
+    ) -> HashMap<ResourceType, Vec<(usize, f64)>> {
+        let mut net: HashMap<ResourceType, HashMap<usize, f64>> = HashMap::new();
+        for (index, recipe) in recipe_extra.iter().enumerate() {
+            for item in &recipe.recipe.items {
+                *net.entry(item.resource_type)
+                    .or_default()
+                    .entry(index)
+                    .or_default() -= item.num / recipe.recipe.time;
+            }
+            for result in &recipe.recipe.results {
+                *net.entry(result.resource_type)
+                    .or_default()
+                    .entry(index)
+                    .or_default() += result.num / recipe.recipe.time;
+            }
+        }
+        net.into_iter()
+            .map(|(resource_type, coefficients)| {
+                let mut coefficients: Vec<_> = coefficients.into_iter().collect();
+                coefficients.sort_unstable_by_key(|&(index, _)| index);
+                (resource_type, coefficients)
+            })
+            .collect()
+    }
+
+    // 目标物品加权净产量的系数
+    fn throughput_coefficients(
+        &self,
+        net: &HashMap<ResourceType, Vec<(usize, f64)>>,
+    ) -> Vec<(usize, f64)> {
+        self.targets
+            .iter()
+            .flat_map(|target| {
+                net.get(&target.resource_type)
+                    .into_iter()
+                    .flatten()
+                    .map(|&(index, coefficient)| (index, coefficient * target.weight))
+            })
+            .collect()
+    }
+}
+
+/// 把最优解移动到可行域的顶点上
+///
+/// 内点法在多个公式代价相同时会给出它们的混合，这里反复寻找只改变非零变量、
+/// 且保持所有紧约束不变的方向，沿着不增加目标的一侧移动，直到某个变量或约束到达边界。
+/// 当非零变量对应的紧约束列线性无关时，解就是基本解，每个自由度最多使用一个公式。
+pub fn crossover(objective: &[f64], rows: &[Row], mut values: Vec<f64>) -> Vec<f64> {
+    // 每次移动至少让一个变量归零或一个约束变紧，次数有限
+    for _ in 0..=values.len() + rows.len() {
+        for value in &mut values {
+            if *value <= THRESHOLD {
+                *value = 0.0;
+            }
+        }
+        let support: Vec<_> = (0..values.len())
+            .filter(|&index| values.get(index).is_some_and(|&value| value > 0.0))
+            .collect();
+        let (active, inactive): (Vec<_>, Vec<_>) = rows.iter().partition(|row| {
+            row.value(&values) - row.lower <= THRESHOLD * row.lower.abs().max(1.0)
+        });
+
+        let Some(mut direction) = null_direction(&active, &support, values.len()) else {
+            break;
+        };
+
+        // 沿不增加目标的方向移动
+        let slope = dot(objective, &direction);
+        if slope > 0.0 {
+            negate(&mut direction);
+        }
+        let step = match max_step(&values, &inactive, &direction) {
+            Some(step) => step,
+            None if slope.abs() <= THRESHOLD => {
+                negate(&mut direction);
+                match max_step(&values, &inactive, &direction) {
+                    Some(step) => step,
+                    None => break,
+                }
+            }
+            None => break,
+        };
+
+        for (value, d) in values.iter_mut().zip(&direction) {
+            *value += step * d;
+        }
+    }
+    values
+}
+
+fn negate(direction: &mut [f64]) {
+    for d in direction {
+        *d = -*d;
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+// 在变量非负、松约束保持满足的前提下，沿方向最多能走多远
+fn max_step(values: &[f64], inactive: &[&Row], direction: &[f64]) -> Option<f64> {
+    let variable_limits = values
+        .iter()
+        .zip(direction)
+        .filter(|(_, &d)| d < -THRESHOLD)
+        .map(|(value, d)| value / -d);
+    let row_limits = inactive.iter().filter_map(|row| {
+        let rate = row.value(direction);
+        (rate < -THRESHOLD).then(|| (row.value(values) - row.lower) / -rate)
+    });
+    variable_limits.chain(row_limits).reduce(f64::min)
+}
+
+// 找一个只在非零变量上取值、且不改变紧约束的非零方向，不存在时说明已经是基本解
+fn null_direction(active: &[&Row], support: &[usize], len: usize) -> Option<Vec<f64>> {
+    // 紧约束在非零变量上的稠密子矩阵
+    let column: HashMap<usize, usize> = support
+        .iter()
+        .enumerate()
+        .map(|(column, &index)| (index, column))
+        .collect();
+    let mut matrix: Vec<Vec<f64>> = active
+        .iter()
+        .map(|row| {
+            let mut dense = vec![0.0; support.len()];
+            for (index, coefficient) in &row.coefficients {
+                if let Some(entry) = column.get(index).and_then(|&c| dense.get_mut(c)) {
+                    *entry += coefficient;
+                }
+            }
+            dense
+        })
+        .collect();
+
+    // 部分选主元的高斯消元，化为简化行阶梯形
+    let mut pivots = Vec::new();
+    let mut rank = 0;
+    for c in 0..support.len() {
+        let Some((pivot_row, pivot_value)) = matrix
+            .iter()
+            .enumerate()
+            .skip(rank)
+            .map(|(r, row)| (r, row.get(c).copied().unwrap_or(0.0)))
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        else {
+            break;
+        };
+        if pivot_value.abs() <= THRESHOLD {
+            continue;
+        }
+        matrix.swap(rank, pivot_row);
+        let Some(pivot) = matrix.get(rank).cloned() else {
+            break;
+        };
+        let pivot: Vec<f64> = pivot.iter().map(|x| x / pivot_value).collect();
+        for (r, row) in matrix.iter_mut().enumerate() {
+            if r == rank {
+                row.clone_from(&pivot);
+                continue;
+            }
+            let factor = row.get(c).copied().unwrap_or(0.0);
+            if factor != 0.0 {
+                for (x, p) in row.iter_mut().zip(&pivot) {
+                    *x -= factor * p;
+                }
+            }
+        }
+        pivots.push(c);
+        rank += 1;
+    }
+
+    // 任取一个自由列，令其为1，主元列由消元结果确定
+    let free = (0..support.len()).find(|c| !pivots.contains(c))?;
+    let mut direction = vec![0.0; len];
+    if let Some(d) = support
+        .get(free)
+        .and_then(|&index| direction.get_mut(index))
+    {
+        *d = 1.0;
+    }
+    for (row, &c) in matrix.iter().zip(&pivots) {
+        let coefficient = row.get(free).copied().unwrap_or(0.0);
+        if let Some(d) = support.get(c).and_then(|&index| direction.get_mut(index)) {
+            *d = -coefficient;
+        }
+    }
+    Some(direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(coefficients: &[(usize, f64)], lower: f64) -> Row {
+        Row {
+            coefficients: coefficients.to_vec(),
+            lower,
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn moves_mixture_to_vertex() {
+        // 两个代价相同的公式混合使用，移动后只剩一个
+        let rows = [row(&[(0, 1.0), (1, 1.0)], 2.0)];
+        let values = crossover(&[1.0, 1.0], &rows, vec![1.0, 1.0]);
+        assert!(values.contains(&0.0), "{values:?}");
+        assert!((values.iter().sum::<f64>() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn moves_toward_lower_cost() {
+        let rows = [row(&[(0, 1.0), (1, 1.0)], 2.0)];
+        let values = crossover(&[2.0, 1.0], &rows, vec![1.0, 1.0]);
+        assert_close(&values, &[0.0, 2.0]);
+    }
+
+    #[test]
+    fn keeps_degenerate_vertex() {
+        // (1, 0)上三个约束都是紧的，已经是基本解
+        let rows = [
+            row(&[(0, 1.0), (1, 1.0)], 1.0),
+            row(&[(0, 1.0)], 1.0),
+            row(&[(0, 1.0), (1, 2.0)], 1.0),
+        ];
+        let values = crossover(&[1.0, 2.0], &rows, vec![1.0, 0.0]);
+        assert_close(&values, &[1.0, 0.0]);
+    }
+
+    #[test]
+    fn stops_at_inactive_row() {
+        // 沿x0 + x1 = 2移动时，松约束x0 >= 0.5先到达边界
+        let rows = [row(&[(0, 1.0), (1, 1.0)], 2.0), row(&[(0, 1.0)], 0.5)];
+        let values = crossover(&[2.0, 1.0], &rows, vec![1.0, 1.0]);
+        assert_close(&values, &[0.5, 1.5]);
+    }
+
+    #[test]
+    fn null_direction_of_full_rank_is_none() {
+        let rows = [row(&[(0, 1.0)], 1.0), row(&[(1, 1.0)], 1.0)];
+        let active: Vec<_> = rows.iter().collect();
+        assert!(null_direction(&active, &[0, 1], 2).is_none());
+    }
+
+    #[test]
+    fn null_direction_keeps_active_rows() {
+        let rows = [row(&[(0, 1.0), (1, 1.0), (2, 1.0)], 3.0)];
+        let active: Vec<_> = rows.iter().collect();
+        let Some(direction) = null_direction(&active, &[0, 2], 3) else {
+            panic!("two variables on one row have a free direction");
+        };
+        assert!(rows.iter().all(|row| row.value(&direction).abs() < 1e-12));
+        assert_close(&direction, &[-1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn max_step_respects_variables_and_rows() {
+        let rows = [row(&[(0, 1.0)], 0.5)];
+        let inactive: Vec<_> = rows.iter().collect();
+        assert_eq!(max_step(&[1.0, 1.0], &inactive, &[-1.0, 1.0]), Some(0.5));
+        assert_eq!(max_step(&[1.0, 1.0], &[], &[-1.0, 1.0]), Some(1.0));
+        assert_eq!(max_step(&[1.0, 1.0], &[], &[1.0, 1.0]), None);
+    }
+}
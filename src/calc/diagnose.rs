@@ -0,0 +1,174 @@
+use std::fmt;
+
+use good_lp::{variable, variables, Expression, ResolutionError, Solution, SolverModel};
+
+#[cfg(feature = "microlp")]
+use super::backend::Microlp;
+use super::{
+    backend::{Backend, Clarabel},
+    constraint::constraint_needs_elastic,
+    lexicographic::{constraint_bounds, Bound, Goal},
+    translator::from_values,
+    Problem, ProcessedRecipes, RecipeBinding, SolverBackend, THRESHOLD,
+};
+use crate::{
+    dsp::{item::Resource, recipe::Recipe},
+    error::DspCalError::{self, DiagnosedLpSolverError, LpSolverError},
+};
+
+// 诊断无界问题时每个公式的建筑数量上限，达到一半即视为无界的来源
+const MAX_NUM: f64 = 1e6;
+
+/// 求解失败的诊断信息
+#[derive(Clone, Debug)]
+pub enum Diagnostic {
+    /// 无可行解，列出补足后可以求解的最小产量缺口，单位为每帧
+    Infeasible(Vec<Resource>),
+    /// 目标无界，列出数量可以无限增长的公式
+    Unbounded(Vec<Recipe>),
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Infeasible(shortfalls) => {
+                write!(f, "shortfalls:")?;
+                for shortfall in shortfalls {
+                    write!(
+                        f,
+                        "\n{}: {}",
+                        shortfall.resource_type,
+                        shortfall.resource_type.format_rate(shortfall.num, 6)
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Unbounded(recipes) => {
+                write!(f, "unbounded recipes:")?;
+                for recipe in recipes {
+                    write!(f, "\n{}", recipe.info.name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Problem {
+    // 根据求解器返回的错误进行诊断，诊断失败时原样返回求解器错误，
+    // `failed_extra`、`goal`和`bounds`是求解失败的那次线性规划所用的
+    pub(super) fn diagnose(
+        &self,
+        error: ResolutionError,
+        failed_extra: &[RecipeBinding],
+        goal: Goal,
+        bounds: &[Bound],
+    ) -> DspCalError {
+        let diagnostic = match error {
+            ResolutionError::Infeasible => self.diagnose_infeasible(),
+            ResolutionError::Unbounded => self.diagnose_unbounded(failed_extra, goal, bounds),
+            _ => None,
+        };
+        match diagnostic {
+            Some(diagnostic) => DiagnosedLpSolverError(error, diagnostic),
+            None => LpSolverError(error),
+        }
+    }
+
+    fn diagnose_infeasible(&self) -> Option<Diagnostic> {
+        match self.settings.backend {
+            SolverBackend::Clarabel => self.diagnose_infeasible_with::<Clarabel>(),
+            #[cfg(feature = "microlp")]
+            SolverBackend::Microlp => self.diagnose_infeasible_with::<Microlp>(),
+        }
+    }
+
+    // 给每个平衡约束和供给约束加上松弛变量，最小化松弛总量，得到最小的产量缺口
+    fn diagnose_infeasible_with<B: Backend>(&self) -> Option<Diagnostic> {
+        let mut model = variables!();
+        let recipe_extra = self.bind(&mut model);
+
+        // 合并同种资源的需求，没有需求的产物需求量为0
+        let mut needs: Vec<Resource> = self
+            .balanced_productions()
+            .into_iter()
+            .map(|resource_type| Resource {
+                resource_type,
+                num: 0.0,
+            })
+            .collect();
+        for need in self.balanced_needs() {
+            match needs
+                .iter_mut()
+                .find(|merged| merged.resource_type == need.resource_type)
+            {
+                Some(merged) => merged.num += need.num,
+                None => needs.push(need),
+            }
+        }
+
+        // 供给约束同样可以松弛，缺口即为需要额外输入的原矿
+        needs.extend(
+            self.remaining_supplies()
+                .into_iter()
+                .map(|supply| Resource {
+                    resource_type: supply.resource_type,
+                    num: -supply.num,
+                }),
+        );
+
+        let slacks: Vec<_> = needs
+            .iter()
+            .map(|_| model.add(variable().min(0.0)))
+            .collect();
+        let objective = slacks.iter().copied().sum::<Expression>();
+
+        let mut lp_problem = B::model(model.minimise(objective), &self.settings);
+        let processed = ProcessedRecipes::new(&recipe_extra);
+        let _ref_constraint_need =
+            constraint_needs_elastic(&processed, &mut lp_problem, &needs, &slacks);
+
+        // 固定的建筑数量不能松弛，由它们造成的缺口体现在平衡约束和供给约束上
+        constraint_bounds(
+            &processed,
+            &mut lp_problem,
+            &recipe_extra,
+            &self.targets,
+            &self.fixed_bounds(&recipe_extra),
+        );
+        let lp_solution = lp_problem.solve().ok()?;
+
+        let shortfalls: Vec<_> = needs
+            .iter()
+            .zip(&slacks)
+            .map(|(need, &slack)| Resource {
+                resource_type: need.resource_type,
+                num: lp_solution.value(slack),
+            })
+            .filter(|shortfall| shortfall.num > THRESHOLD)
+            .collect();
+        (!shortfalls.is_empty()).then_some(Diagnostic::Infeasible(shortfalls))
+    }
+
+    // 按失败阶段的权重和约束，给每个公式加上数量上限后重新求解，顶到上限的公式就是无界的来源
+    fn diagnose_unbounded(
+        &self,
+        failed_extra: &[RecipeBinding],
+        goal: Goal,
+        bounds: &[Bound],
+    ) -> Option<Diagnostic> {
+        let mut model = variables!();
+        let weights = failed_extra.iter().map(|recipe| recipe.weight).collect();
+        let recipe_extra = self.bind_weighted(&mut model, weights);
+        let values = self
+            .solve_lp(model, &recipe_extra, goal, bounds, Some(MAX_NUM))
+            .ok()?;
+
+        let recipes: Vec<_> = from_values(&recipe_extra, &values)
+            .into_iter()
+            .filter(|solution| solution.num > MAX_NUM / 2.0)
+            .map(|solution| solution.recipe)
+            .collect();
+        (!recipes.is_empty()).then_some(Diagnostic::Unbounded(recipes))
+    }
+}
@@ -0,0 +1,626 @@
+use std::{cmp::Ordering, collections::HashSet};
+
+use num::{BigInt, BigRational, FromPrimitive, One, Signed, ToPrimitive, Zero};
+
+use super::{
+    crossover::{crossover, Row},
+    Problem, THRESHOLD,
+};
+use crate::{
+    dsp::{item::Resource, recipe::Recipe},
+    error::DspCalError::{self, ExactSolverError},
+};
+
+// 把浮点数转换为分数时允许的相对误差
+const RATIONAL_TOLERANCE: f64 = 1e-12;
+
+// 单纯形法的最大迭代次数
+const MAX_ITERATIONS: usize = 10_000;
+
+/// 精确解，建筑数量为分数
+#[derive(Clone, Debug)]
+pub struct ExactSolution {
+    pub recipe: Recipe,
+    pub num: BigRational,
+}
+
+impl ExactSolution {
+    /// 该公式一项原料或产物的精确速率，单位为每帧
+    #[must_use]
+    pub fn rate(&self, resource: &Resource) -> BigRational {
+        &self.num * to_rational(resource.num) / to_rational(self.recipe.time)
+    }
+}
+
+/// 把浮点数转换为误差足够小的最简分数
+///
+/// 游戏数据大多是简单的分数，例如`20.0 / 1.5`，连分数展开可以把浮点误差还原掉
+#[must_use]
+pub fn to_rational(x: f64) -> BigRational {
+    if x == 0.0 || !x.is_finite() {
+        return BigRational::zero();
+    }
+    let tolerance = RATIONAL_TOLERANCE * x.abs();
+    let (mut h0, mut h1) = (BigInt::zero(), BigInt::one());
+    let (mut k0, mut k1) = (BigInt::one(), BigInt::zero());
+    let mut remainder = x;
+    for _ in 0..64 {
+        let a = remainder.floor();
+        let Some(a_int) = BigInt::from_f64(a) else {
+            break;
+        };
+        (h0, h1) = (h1.clone(), &a_int * &h1 + h0);
+        (k0, k1) = (k1.clone(), &a_int * &k1 + k0);
+        let approx = BigRational::new(h1.clone(), k1.clone());
+        if approx
+            .to_f64()
+            .is_some_and(|approx| (approx - x).abs() <= tolerance)
+        {
+            return approx;
+        }
+        let fraction = remainder - a;
+        if fraction == 0.0 {
+            return approx;
+        }
+        remainder = 1.0 / fraction;
+    }
+    BigRational::from_float(x).unwrap_or_else(BigRational::zero)
+}
+
+impl Problem {
+    /// 精确求解
+    ///
+    /// 先用浮点求解器得到顶点解，以它的基为起点，在分数上用单纯形法重新求解，
+    /// 得到精确的建筑数量。浮点解的基通常已经最优，只需要验证。
+    ///
+    /// # Errors
+    /// 浮点求解失败时返回相应的错误；浮点解不是顶点，或者它的基在精确意义下不可行时返回错误
+    pub fn solve_exact(&self) -> Result<Vec<ExactSolution>, DspCalError> {
+        if self.recipes.len() != self.weights.len() {
+            return Err(DspCalError::MismatchedRecipeWeights(
+                self.recipes.len(),
+                self.weights.len(),
+            ));
+        }
+        self.validate()?;
+
+        let mut final_solve = self.solve_final()?;
+        if !self.settings.crossover {
+            final_solve.values = crossover(
+                &self.objective_coefficients(&final_solve.recipe_extra, final_solve.goal),
+                &self.rows(&final_solve.recipe_extra, &final_solve.bounds, None),
+                final_solve.values,
+            );
+        }
+
+        let objective = self.objective_coefficients(&final_solve.recipe_extra, final_solve.goal);
+        let rows = self.rows(&final_solve.recipe_extra, &final_solve.bounds, None);
+        let values = ExactSimplex::new(&objective, &rows).solve(&final_solve.values)?;
+
+        Ok(final_solve
+            .recipe_extra
+            .into_iter()
+            .zip(values)
+            .filter(|(_, num)| num.is_positive())
+            .map(|(recipe, num)| ExactSolution {
+                recipe: recipe.recipe,
+                num,
+            })
+            .collect())
+    }
+}
+
+/// 分数上的单纯形法
+///
+/// 约束为`A x - s = b`，`x`和松弛变量`s`都非负。基由非零的公式变量和非紧约束的松弛变量组成，
+/// 只需要在紧约束和基中的公式变量构成的方阵上求解。
+struct ExactSimplex {
+    objective: Vec<BigRational>,
+    rows: Vec<Vec<(usize, BigRational)>>,
+    lower: Vec<BigRational>,
+}
+
+/// 进基变量
+#[derive(Clone, Copy)]
+enum Entering {
+    Recipe(usize),
+    Slack(usize),
+}
+
+/// 出基变量
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Leaving {
+    Recipe(usize),
+    Slack(usize),
+}
+
+impl ExactSimplex {
+    fn new(objective: &[f64], rows: &[Row]) -> Self {
+        Self {
+            objective: objective.iter().map(|&c| to_rational(c)).collect(),
+            rows: rows
+                .iter()
+                .map(|row| {
+                    row.coefficients
+                        .iter()
+                        .map(|&(index, coefficient)| (index, to_rational(coefficient)))
+                        .collect()
+                })
+                .collect(),
+            lower: rows.iter().map(|row| to_rational(row.lower)).collect(),
+        }
+    }
+
+    fn coefficient(&self, row: usize, column: usize) -> BigRational {
+        self.rows
+            .get(row)
+            .into_iter()
+            .flatten()
+            .filter(|(index, _)| *index == column)
+            .map(|(_, coefficient)| coefficient.clone())
+            .sum()
+    }
+
+    fn row_value(&self, row: usize, values: &[BigRational]) -> BigRational {
+        self.rows
+            .get(row)
+            .into_iter()
+            .flatten()
+            .filter_map(|(index, coefficient)| values.get(*index).map(|value| coefficient * value))
+            .sum()
+    }
+
+    fn slack(&self, row: usize, values: &[BigRational]) -> BigRational {
+        self.row_value(row, values)
+            - self
+                .lower
+                .get(row)
+                .cloned()
+                .unwrap_or_else(BigRational::zero)
+    }
+
+    // 从浮点解出发求解，返回每个公式变量的精确值
+    fn solve(&self, start: &[f64]) -> Result<Vec<BigRational>, DspCalError> {
+        let (mut basic, mut tight) = self.initial_basis(start)?;
+
+        for _ in 0..MAX_ITERATIONS {
+            let values = self.primal(start.len(), &basic, &tight)?;
+            if let Some(row) = (0..self.rows.len())
+                .filter(|row| !tight.contains(row))
+                .find(|&row| self.slack(row, &values).is_negative())
+            {
+                return Err(ExactSolverError(format!(
+                    "the basis is infeasible at row {row}"
+                )));
+            }
+            if values.iter().any(Signed::is_negative) {
+                return Err(ExactSolverError(String::from(
+                    "the basis has negative building counts",
+                )));
+            }
+
+            let duals = self.duals(&basic, &tight)?;
+            let Some(entering) = self.entering(start.len(), &basic, &tight, &duals) else {
+                return Ok(values);
+            };
+            let direction = self.direction(start.len(), &basic, &tight, entering)?;
+            let leaving = self.leaving(&values, &direction, &basic, &tight)?;
+
+            match entering {
+                Entering::Recipe(column) => basic.push(column),
+                Entering::Slack(row) => tight.retain(|&r| r != row),
+            }
+            match leaving {
+                Leaving::Recipe(column) => basic.retain(|&c| c != column),
+                Leaving::Slack(row) => tight.push(row),
+            }
+        }
+        Err(ExactSolverError(String::from("too many iterations")))
+    }
+
+    // 浮点解中非零的公式变量进基，从紧约束中选出与它们线性无关的同样数量的约束
+    fn initial_basis(&self, start: &[f64]) -> Result<(Vec<usize>, Vec<usize>), DspCalError> {
+        let basic: Vec<_> = (0..start.len())
+            .filter(|&index| start.get(index).is_some_and(|&value| value > THRESHOLD))
+            .collect();
+        let start_rational: Vec<_> = start.iter().map(|&value| to_rational(value)).collect();
+        // 只从浮点意义下的紧约束中选择，越紧的约束越优先
+        let mut candidates: Vec<_> = (0..self.rows.len())
+            .map(|row| (row, self.slack(row, &start_rational).abs()))
+            .filter(|(row, slack)| {
+                let lower = self
+                    .lower
+                    .get(*row)
+                    .and_then(ToPrimitive::to_f64)
+                    .unwrap_or(0.0);
+                slack
+                    .to_f64()
+                    .is_some_and(|slack| slack <= THRESHOLD * lower.abs().max(1.0))
+            })
+            .collect();
+        candidates.sort_by(|(_, a), (_, b)| a.cmp(b));
+
+        let mut echelon: Vec<(usize, Vec<BigRational>)> = Vec::new();
+        let mut tight = Vec::new();
+        for (row, _) in candidates {
+            if tight.len() == basic.len() {
+                break;
+            }
+            let mut dense: Vec<_> = basic
+                .iter()
+                .map(|&column| self.coefficient(row, column))
+                .collect();
+            for (pivot, reduced) in &echelon {
+                let factor = dense.get(*pivot).cloned().unwrap_or_else(BigRational::zero);
+                if !factor.is_zero() {
+                    for (x, r) in dense.iter_mut().zip(reduced) {
+                        *x -= &factor * r;
+                    }
+                }
+            }
+            if let Some(pivot) = dense.iter().position(|x| !x.is_zero()) {
+                let pivot_value = dense.get(pivot).cloned().unwrap_or_else(BigRational::one);
+                let reduced = dense.iter().map(|x| x / &pivot_value).collect();
+                echelon.push((pivot, reduced));
+                tight.push(row);
+            }
+        }
+        if tight.len() == basic.len() {
+            Ok((basic, tight))
+        } else {
+            Err(ExactSolverError(String::from(
+                "the floating point solution is not a vertex",
+            )))
+        }
+    }
+
+    // 基矩阵，行为紧约束，列为基中的公式变量
+    fn basis_matrix(&self, basic: &[usize], tight: &[usize]) -> Vec<Vec<BigRational>> {
+        tight
+            .iter()
+            .map(|&row| {
+                basic
+                    .iter()
+                    .map(|&column| self.coefficient(row, column))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // 求解基对应的公式变量，非基变量为0
+    fn primal(
+        &self,
+        len: usize,
+        basic: &[usize],
+        tight: &[usize],
+    ) -> Result<Vec<BigRational>, DspCalError> {
+        let rhs = tight
+            .iter()
+            .map(|&row| {
+                self.lower
+                    .get(row)
+                    .cloned()
+                    .unwrap_or_else(BigRational::zero)
+            })
+            .collect();
+        let basic_values = solve_linear(self.basis_matrix(basic, tight), rhs)?;
+        let mut values = vec![BigRational::zero(); len];
+        for (&column, value) in basic.iter().zip(basic_values) {
+            if let Some(entry) = values.get_mut(column) {
+                *entry = value;
+            }
+        }
+        Ok(values)
+    }
+
+    // 紧约束的对偶变量，满足`B^T y = c_B`
+    fn duals(&self, basic: &[usize], tight: &[usize]) -> Result<Vec<BigRational>, DspCalError> {
+        let matrix = self.basis_matrix(basic, tight);
+        let transposed = (0..basic.len())
+            .map(|column| {
+                matrix
+                    .iter()
+                    .map(|row| row.get(column).cloned().unwrap_or_else(BigRational::zero))
+                    .collect()
+            })
+            .collect();
+        let rhs = basic
+            .iter()
+            .map(|&column| {
+                self.objective
+                    .get(column)
+                    .cloned()
+                    .unwrap_or_else(BigRational::zero)
+            })
+            .collect();
+        solve_linear(transposed, rhs)
+    }
+
+    // 按Bland规则选择检验数为负的最小下标变量进基，不存在时已经最优
+    fn entering(
+        &self,
+        len: usize,
+        basic: &[usize],
+        tight: &[usize],
+        duals: &[BigRational],
+    ) -> Option<Entering> {
+        let basic: HashSet<_> = basic.iter().copied().collect();
+        let recipe = (0..len)
+            .filter(|column| !basic.contains(column))
+            .find(|&column| {
+                let reduced = self
+                    .objective
+                    .get(column)
+                    .cloned()
+                    .unwrap_or_else(BigRational::zero)
+                    - tight
+                        .iter()
+                        .zip(duals)
+                        .map(|(&row, dual)| dual * self.coefficient(row, column))
+                        .sum::<BigRational>();
+                reduced.is_negative()
+            });
+        recipe.map(Entering::Recipe).or_else(|| {
+            let mut rows: Vec<_> = tight.iter().zip(duals).collect();
+            rows.sort_by_key(|(&row, _)| row);
+            rows.into_iter()
+                .find(|(_, dual)| dual.is_negative())
+                .map(|(&row, _)| Entering::Slack(row))
+        })
+    }
+
+    // 进基变量增加1时所有公式变量的变化量
+    fn direction(
+        &self,
+        len: usize,
+        basic: &[usize],
+        tight: &[usize],
+        entering: Entering,
+    ) -> Result<Vec<BigRational>, DspCalError> {
+        let (rhs, sign): (Vec<_>, _) = match entering {
+            Entering::Recipe(column) => (
+                tight
+                    .iter()
+                    .map(|&row| self.coefficient(row, column))
+                    .collect(),
+                -BigRational::one(),
+            ),
+            Entering::Slack(entering_row) => (
+                tight
+                    .iter()
+                    .map(|&row| {
+                        if row == entering_row {
+                            BigRational::one()
+                        } else {
+                            BigRational::zero()
+                        }
+                    })
+                    .collect(),
+                BigRational::one(),
+            ),
+        };
+        let basic_direction = solve_linear(self.basis_matrix(basic, tight), rhs)?;
+        let mut direction = vec![BigRational::zero(); len];
+        for (&column, value) in basic.iter().zip(basic_direction) {
+            if let Some(entry) = direction.get_mut(column) {
+                *entry = &sign * value;
+            }
+        }
+        if let Entering::Recipe(column) = entering {
+            if let Some(entry) = direction.get_mut(column) {
+                *entry = BigRational::one();
+            }
+        }
+        Ok(direction)
+    }
+
+    // 比值检验，最先降到0的基变量出基，并列时按Bland规则选最小下标
+    fn leaving(
+        &self,
+        values: &[BigRational],
+        direction: &[BigRational],
+        basic: &[usize],
+        tight: &[usize],
+    ) -> Result<Leaving, DspCalError> {
+        let recipes = basic.iter().filter_map(|&column| {
+            let d = direction.get(column)?;
+            d.is_negative().then(|| {
+                (
+                    values
+                        .get(column)
+                        .cloned()
+                        .unwrap_or_else(BigRational::zero)
+                        / -d,
+                    Leaving::Recipe(column),
+                )
+            })
+        });
+        let slacks = (0..self.rows.len())
+            .filter(|row| !tight.contains(row))
+            .filter_map(|row| {
+                let rate = self.row_value(row, direction);
+                rate.is_negative()
+                    .then(|| (self.slack(row, values) / -rate, Leaving::Slack(row)))
+            });
+        recipes
+            .chain(slacks)
+            .min_by(|(a, la), (b, lb)| match a.cmp(b) {
+                Ordering::Equal => la.cmp(lb),
+                ordering => ordering,
+            })
+            .map(|(_, leaving)| leaving)
+            .ok_or_else(|| ExactSolverError(String::from("the problem is unbounded")))
+    }
+}
+
+// 高斯消元求解方阵线性方程组
+fn solve_linear(
+    matrix: Vec<Vec<BigRational>>,
+    rhs: Vec<BigRational>,
+) -> Result<Vec<BigRational>, DspCalError> {
+    let n = rhs.len();
+    if matrix.len() != n || matrix.iter().any(|row| row.len() != n) {
+        return Err(ExactSolverError(String::from("the basis is not square")));
+    }
+    let singular = || ExactSolverError(String::from("the basis is singular"));
+
+    // 增广矩阵，最后一列为右端项
+    let mut rows: Vec<Vec<BigRational>> = matrix
+        .into_iter()
+        .zip(rhs)
+        .map(|(mut row, b)| {
+            row.push(b);
+            row
+        })
+        .collect();
+    for column in 0..n {
+        let pivot = (column..n)
+            .find(|&row| {
+                rows.get(row)
+                    .and_then(|row| row.get(column))
+                    .is_some_and(|x| !x.is_zero())
+            })
+            .ok_or_else(singular)?;
+        rows.swap(column, pivot);
+        let mut pivot_row = rows.get(column).cloned().ok_or_else(singular)?;
+        let pivot_value = pivot_row.get(column).cloned().ok_or_else(singular)?;
+        for x in &mut pivot_row {
+            *x /= &pivot_value;
+        }
+        for (index, row) in rows.iter_mut().enumerate() {
+            if index == column {
+                row.clone_from(&pivot_row);
+                continue;
+            }
+            let factor = match row.get(column) {
+                Some(factor) if !factor.is_zero() => factor.clone(),
+                _ => continue,
+            };
+            for (x, p) in row.iter_mut().zip(&pivot_row).skip(column) {
+                *x -= &factor * p;
+            }
+        }
+    }
+    Ok(rows
+        .into_iter()
+        .map(|mut row| row.pop().unwrap_or_else(BigRational::zero))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratio(numer: i64, denom: i64) -> BigRational {
+        BigRational::new(BigInt::from(numer), BigInt::from(denom))
+    }
+
+    fn row(coefficients: &[(usize, f64)], lower: f64) -> Row {
+        Row {
+            coefficients: coefficients.to_vec(),
+            lower,
+        }
+    }
+
+    #[test]
+    fn to_rational_recovers_simple_fractions() {
+        assert_eq!(to_rational(20.0 / 1.5), ratio(40, 3));
+        assert_eq!(to_rational(-0.75), ratio(-3, 4));
+        assert_eq!(to_rational(0.0), BigRational::zero());
+        assert_eq!(to_rational(f64::NAN), BigRational::zero());
+    }
+
+    #[test]
+    fn to_rational_removes_floating_point_error() {
+        // 0.1和1/3都不能用浮点数精确表示，直接转换得到的是二进制近似值
+        assert_ne!(BigRational::from_float(0.1), Some(ratio(1, 10)));
+        assert_eq!(to_rational(0.1), ratio(1, 10));
+        assert_eq!(to_rational(1.0 / 3.0), ratio(1, 3));
+        assert_eq!(to_rational(0.1 + 0.2), ratio(3, 10));
+    }
+
+    #[test]
+    fn solve_linear_swaps_zero_pivots() {
+        let matrix = vec![
+            vec![ratio(0, 1), ratio(1, 1)],
+            vec![ratio(3, 1), ratio(1, 1)],
+        ];
+        let rhs = vec![ratio(2, 1), ratio(5, 1)];
+        let Ok(x) = solve_linear(matrix, rhs) else {
+            panic!("the system is regular");
+        };
+        assert_eq!(x, vec![ratio(1, 1), ratio(2, 1)]);
+    }
+
+    #[test]
+    fn solve_linear_keeps_fractions_exact() {
+        let matrix = vec![
+            vec![ratio(3, 1), ratio(1, 1)],
+            vec![ratio(1, 1), ratio(3, 1)],
+        ];
+        let rhs = vec![ratio(1, 1), ratio(0, 1)];
+        let Ok(x) = solve_linear(matrix, rhs) else {
+            panic!("the system is regular");
+        };
+        assert_eq!(x, vec![ratio(3, 8), ratio(-1, 8)]);
+    }
+
+    #[test]
+    fn solve_linear_rejects_singular_and_non_square() {
+        let singular = vec![
+            vec![ratio(1, 1), ratio(2, 1)],
+            vec![ratio(2, 1), ratio(4, 1)],
+        ];
+        assert!(solve_linear(singular, vec![ratio(1, 1), ratio(2, 1)]).is_err());
+        let non_square = vec![vec![ratio(1, 1), ratio(2, 1)]];
+        assert!(solve_linear(non_square, vec![ratio(1, 1)]).is_err());
+    }
+
+    #[test]
+    fn simplex_accepts_degenerate_vertex() {
+        // 最优点(1, 0)上有三个紧约束，但只有一个基变量
+        let rows = [
+            row(&[(0, 1.0), (1, 1.0)], 1.0),
+            row(&[(0, 1.0)], 1.0),
+            row(&[(0, 1.0), (1, 2.0)], 1.0),
+        ];
+        let simplex = ExactSimplex::new(&[1.0, 2.0], &rows);
+        let Ok(values) = simplex.solve(&[1.0, 0.0]) else {
+            panic!("the vertex is optimal");
+        };
+        assert_eq!(values, vec![ratio(1, 1), ratio(0, 1)]);
+    }
+
+    #[test]
+    fn simplex_pivots_with_blands_rule_on_ties() {
+        // 从非最优顶点(1, 0)出发，x1进基时x0和第二个约束的比值相同，
+        // Bland规则选下标最小的x0出基，到达退化的最优顶点(0, 1)
+        let rows = [
+            row(&[(0, 1.0), (1, 1.0)], 1.0),
+            row(&[(0, 2.0), (1, 1.0)], 1.0),
+        ];
+        let simplex = ExactSimplex::new(&[2.0, 1.0], &rows);
+        let Ok(values) = simplex.solve(&[1.0, 0.0]) else {
+            panic!("the problem is bounded");
+        };
+        assert_eq!(values, vec![ratio(0, 1), ratio(1, 1)]);
+    }
+
+    #[test]
+    fn simplex_corrects_floating_point_start() {
+        // 浮点解带有误差，精确解为(1/3, 0)
+        let rows = [row(&[(0, 3.0), (1, 1.0)], 1.0)];
+        let simplex = ExactSimplex::new(&[1.0, 1.0], &rows);
+        let Ok(values) = simplex.solve(&[0.333_333_333_333_4, 0.0]) else {
+            panic!("the vertex is optimal");
+        };
+        assert_eq!(values, vec![ratio(1, 3), ratio(0, 1)]);
+    }
+
+    #[test]
+    fn simplex_rejects_non_vertex_start() {
+        let rows = [row(&[(0, 1.0), (1, 1.0)], 2.0)];
+        let simplex = ExactSimplex::new(&[1.0, 1.0], &rows);
+        assert!(simplex.solve(&[1.0, 1.0]).is_err());
+    }
+}
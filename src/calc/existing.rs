@@ -0,0 +1,31 @@
+use super::{Problem, Solution};
+
+/// 一个公式变体中已建成和新增的建筑数量
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Expansion {
+    pub existing: f64,
+    pub added: f64,
+}
+
+impl Problem {
+    /// 把解中每个公式的建筑数量分为已建成和新增两部分，与解一一对应
+    #[must_use]
+    pub fn expansions(&self, solutions: &[Solution]) -> Vec<Expansion> {
+        solutions
+            .iter()
+            .map(|solution| {
+                let existing = self
+                    .existing_recipes
+                    .iter()
+                    .filter(|existing| existing.matches(&solution.recipe))
+                    .map(|existing| existing.count)
+                    .fold(0.0, |sum, count| sum + count)
+                    .min(solution.num);
+                Expansion {
+                    existing,
+                    added: solution.num - existing,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,101 @@
+use super::{balance, Problem, Solution, THRESHOLD};
+use crate::dsp::{
+    building::BuildingType,
+    item::{IndirectResource, Resource, ResourceType},
+};
+
+/// 外部输入的来源
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportKind {
+    /// 由采矿公式开采，`miners`为矿机数量
+    Mined { miners: f64 },
+    /// 视为原矿或没有公式生产的物品，需要从外部运入
+    Raw,
+    /// 电力，速率为总耗电，`generated`为产线内自己发出的部分，单位为MJ每帧
+    Power { generated: f64 },
+}
+
+/// 一项外部输入，速率的单位为每帧
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Import {
+    pub resource_type: ResourceType,
+    pub rate: f64,
+    pub kind: ImportKind,
+}
+
+/// 列出解中所有从外部获得的资源：采矿得到的原矿、需要运入的物品和电力
+///
+/// 运入的物品是消耗和需求超过产量的部分，包括视为原矿的物品和没有公式生产的物品
+#[must_use]
+pub fn import_report(solutions: &[Solution], needs: &[Resource]) -> Vec<Import> {
+    let mut imports: Vec<Import> = Vec::new();
+
+    // 采矿公式的产物，按物品合并
+    for solution in solutions {
+        if solution.recipe.info.building_type != BuildingType::矿机 {
+            continue;
+        }
+        for result in &solution.recipe.results {
+            let rate = solution.num * result.num / solution.recipe.time;
+            let mined = imports.iter_mut().find(|import| {
+                import.resource_type == result.resource_type
+                    && matches!(import.kind, ImportKind::Mined { .. })
+            });
+            match mined {
+                Some(Import {
+                    rate: total,
+                    kind: ImportKind::Mined { miners },
+                    ..
+                }) => {
+                    *total += rate;
+                    *miners += solution.num;
+                }
+                _ => imports.push(Import {
+                    resource_type: result.resource_type,
+                    rate,
+                    kind: ImportKind::Mined {
+                        miners: solution.num,
+                    },
+                }),
+            }
+        }
+    }
+
+    let balances = balance(solutions, needs);
+    imports.extend(
+        balances
+            .iter()
+            .filter(|balance| matches!(balance.resource_type, ResourceType::Direct(_)))
+            .filter(|balance| balance.import() > THRESHOLD)
+            .map(|balance| Import {
+                resource_type: balance.resource_type,
+                rate: balance.import(),
+                kind: ImportKind::Raw,
+            }),
+    );
+
+    let energy = ResourceType::Indirect(IndirectResource::Energy);
+    if let Some(balance) = balances
+        .iter()
+        .find(|balance| balance.resource_type == energy)
+    {
+        imports.push(Import {
+            resource_type: energy,
+            rate: balance.consumption + balance.demand,
+            kind: ImportKind::Power {
+                generated: balance.production,
+            },
+        });
+    }
+
+    imports
+}
+
+impl Problem {
+    /// 用问题的需求列出解中所有从外部获得的资源
+    #[must_use]
+    pub fn import_report(&self, solutions: &[Solution]) -> Vec<Import> {
+        import_report(solutions, &self.all_needs())
+    }
+}
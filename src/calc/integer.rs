@@ -0,0 +1,437 @@
+use good_lp::{variables, ResolutionError};
+use num::{BigInt, BigRational, Integer, One, Signed, Zero};
+
+use super::{
+    crossover::Row,
+    exact::ExactSolution,
+    lexicographic::{Bound, Goal},
+    translator::from_values,
+    Problem, Solution,
+};
+use crate::error::DspCalError;
+
+// 与整数的距离低于该值的数量视为整数
+const INTEGER_TOLERANCE: f64 = 1e-6;
+
+// 约束的相对容差，用于检查取整后的解是否可行
+const FEASIBILITY_TOLERANCE: f64 = 1e-9;
+
+/// 最小的整数建筑比例
+///
+/// 每个公式的建筑数量都是整数，且没有公约数，`count`个模块恰好满足需求
+#[derive(Clone, Debug)]
+pub struct Module {
+    pub solutions: Vec<ExactSolution>,
+    pub count: BigRational,
+}
+
+/// 整数求解的结果
+///
+/// 分支定界达到节点上限时，`optimal`为false，结果是可行的但未必最优
+pub struct IntegerSolution {
+    pub solutions: Vec<Solution>,
+    pub optimal: bool,
+}
+
+// 分支定界的当前最优整数解
+struct Incumbent {
+    objective: f64,
+    values: Vec<f64>,
+}
+
+impl Problem {
+    /// 整数求解，每个公式变体的建筑数量都是整数
+    ///
+    /// 在线性规划上做分支定界，优先向上取整的分支，以尽快得到可行的整数解。
+    /// 前面阶段的最优值是连续解的，取整后通常达不到，这里只保留它们禁用的公式，
+    /// 按最后一个阶段的权重重新优化。
+    ///
+    /// # Errors
+    /// 连续问题求解失败时返回相应的错误；不存在整数解，
+    /// 或者达到`max_nodes`仍未找到整数解时返回错误
+    pub fn solve_integer(&self) -> Result<IntegerSolution, DspCalError> {
+        if self.recipes.len() != self.weights.len() {
+            return Err(DspCalError::MismatchedRecipeWeights(
+                self.recipes.len(),
+                self.weights.len(),
+            ));
+        }
+        self.validate()?;
+
+        let final_solve = self.solve_final()?;
+        let recipe_extra = final_solve.recipe_extra;
+        let weights: Vec<_> = recipe_extra.iter().map(|recipe| recipe.weight).collect();
+        let goal = self.goal();
+        let bounds: Vec<_> = final_solve
+            .bounds
+            .into_iter()
+            .filter(|bound| matches!(bound, Bound::Unused(_)))
+            .collect();
+
+        let objective = self.objective_coefficients(&recipe_extra, goal);
+        let evaluate = |values: &[f64]| -> f64 {
+            objective
+                .iter()
+                .zip(values)
+                .map(|(coefficient, value)| coefficient * value)
+                .sum()
+        };
+
+        // 直接向上取整通常就是一个可行解，作为初始的上界
+        let rows = self.rows(&recipe_extra, &bounds, None);
+        let ceiled: Vec<_> = final_solve
+            .values
+            .iter()
+            .map(|value| (value - INTEGER_TOLERANCE).ceil().max(0.0))
+            .collect();
+        let mut incumbent = is_feasible(&rows, &ceiled).then(|| Incumbent {
+            objective: evaluate(&ceiled),
+            values: ceiled,
+        });
+
+        // 深度优先搜索，栈中是每个节点在公共约束之外的分支约束
+        let mut stack = vec![Vec::new()];
+        let mut nodes = 0;
+        // 达到节点上限时栈中还有未求解的分支，搜索被截断
+        let mut truncated = false;
+        loop {
+            if nodes >= self.settings.max_nodes {
+                truncated = !stack.is_empty();
+                break;
+            }
+            let Some(branch) = stack.pop() else {
+                break;
+            };
+            nodes += 1;
+
+            let node_bounds: Vec<_> = bounds.iter().chain(&branch).cloned().collect();
+            let Some(values) = self.solve_node(weights.clone(), goal, &node_bounds)? else {
+                continue;
+            };
+
+            // 连续松弛的最优值已经不优于当前整数解，剪枝
+            let relaxed = evaluate(&values);
+            if let Some(incumbent) = &incumbent {
+                let margin = FEASIBILITY_TOLERANCE * incumbent.objective.abs().max(1.0);
+                if relaxed >= incumbent.objective - margin {
+                    continue;
+                }
+            }
+
+            // 按距离整数最远的变量分支
+            let fractional = values
+                .iter()
+                .map(|x| (x - x.round()).abs())
+                .enumerate()
+                .filter(|&(_, distance)| distance > INTEGER_TOLERANCE)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .and_then(|(index, _)| values.get(index).map(|&x| (index, x)));
+            if let Some((index, x)) = fractional {
+                let mut down = branch.clone();
+                down.push(Bound::AtMost(index, x.floor()));
+                let mut up = branch;
+                up.push(Bound::AtLeast(index, x.ceil()));
+                stack.push(down);
+                stack.push(up);
+            } else {
+                let rounded: Vec<_> = values.iter().map(|x| x.round()).collect();
+                incumbent = Some(Incumbent {
+                    objective: evaluate(&rounded),
+                    values: rounded,
+                });
+            }
+        }
+
+        match incumbent {
+            Some(incumbent) => Ok(IntegerSolution {
+                solutions: from_values(&recipe_extra, &incumbent.values),
+                optimal: !truncated,
+            }),
+            None if truncated => Err(DspCalError::BranchAndBoundLimit(self.settings.max_nodes)),
+            None => Err(DspCalError::IntegerInfeasible),
+        }
+    }
+
+    /// 最小的整数建筑比例
+    ///
+    /// 把精确解的建筑数量通分后约去公约数，得到可以整体复制的模块
+    ///
+    /// # Errors
+    /// 与`solve_exact`相同
+    pub fn solve_modular(&self) -> Result<Module, DspCalError> {
+        let solutions = self.solve_exact()?;
+
+        let denominator = solutions
+            .iter()
+            .fold(BigInt::one(), |lcm, solution| lcm.lcm(solution.num.denom()));
+        let numerators: Vec<_> = solutions
+            .iter()
+            .map(|solution| (&solution.num * &denominator).to_integer())
+            .collect();
+        let divisor = numerators
+            .iter()
+            .fold(BigInt::zero(), |gcd, numerator| gcd.gcd(numerator));
+        if !divisor.is_positive() {
+            return Ok(Module {
+                solutions: Vec::new(),
+                count: BigRational::zero(),
+            });
+        }
+
+        Ok(Module {
+            solutions: solutions
+                .into_iter()
+                .zip(numerators)
+                .map(|(solution, numerator)| ExactSolution {
+                    recipe: solution.recipe,
+                    num: BigRational::from_integer(numerator / &divisor),
+                })
+                .collect(),
+            count: BigRational::new(divisor, denominator),
+        })
+    }
+
+    // 求解分支定界的一个节点，不可行时返回None
+    fn solve_node(
+        &self,
+        weights: Vec<f64>,
+        goal: Goal,
+        bounds: &[Bound],
+    ) -> Result<Option<Vec<f64>>, DspCalError> {
+        let mut model = variables!();
+        let recipe_extra = self.bind_weighted(&mut model, weights);
+        match self.solve_lp(model, &recipe_extra, goal, bounds, None) {
+            Ok(values) => Ok(Some(values)),
+            Err(ResolutionError::Infeasible) => Ok(None),
+            Err(error) => Err(DspCalError::LpSolverError(error)),
+        }
+    }
+}
+
+fn is_feasible(rows: &[Row], values: &[f64]) -> bool {
+    rows.iter().all(|row| {
+        row.value(values) - row.lower >= -FEASIBILITY_TOLERANCE * row.lower.abs().max(1.0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        calc::SolverSettings,
+        dsp::{
+            item::Resource,
+            recipe::{Recipe, RecipeFmtInfo},
+        },
+    };
+
+    const X: i16 = 1;
+    const Y: i16 = 2;
+    const ORE: i16 = 3;
+
+    fn resource(item_id: i16, num: f64) -> Resource {
+        Resource::from_item_level(item_id, 0, num)
+    }
+
+    fn recipe(name: &str, items: Vec<Resource>, results: Vec<Resource>, time: f64) -> Recipe {
+        Recipe {
+            items,
+            results,
+            time,
+            info: RecipeFmtInfo {
+                name: name.to_string(),
+                ..RecipeFmtInfo::default()
+            },
+        }
+    }
+
+    fn problem(recipes: Vec<Recipe>, needs: Vec<Resource>, supplies: Vec<Resource>) -> Problem {
+        Problem {
+            weights: vec![1.0; recipes.len()],
+            recipes,
+            needs,
+            raw_ores: Vec::new(),
+            disabled_recipes: Vec::new(),
+            supplies,
+            fixed_recipes: Vec::new(),
+            existing_recipes: Vec::new(),
+            targets: Vec::new(),
+            stages: Vec::new(),
+            settings: SolverSettings {
+                verbose: false,
+                ..SolverSettings::default()
+            },
+        }
+    }
+
+    fn counts(solutions: &[Solution]) -> Vec<(String, f64)> {
+        solutions
+            .iter()
+            .map(|solution| (solution.recipe.info.name.clone(), solution.num))
+            .collect()
+    }
+
+    #[test]
+    fn rounds_up_to_whole_buildings() {
+        let problem = problem(
+            vec![recipe("x", Vec::new(), vec![resource(X, 1.0)], 1.0)],
+            vec![resource(X, 2.5)],
+            Vec::new(),
+        );
+        let Ok(integer) = problem.solve_integer() else {
+            panic!("three buildings satisfy the demand");
+        };
+        assert!(integer.optimal);
+        assert_eq!(counts(&integer.solutions), vec![(String::from("x"), 3.0)]);
+    }
+
+    #[test]
+    fn branches_to_cheaper_integer_mix() {
+        // 连续解只用便宜的小公式2.5台，整数解用1台大公式和1台小公式，比3台小公式更便宜
+        let mut problem = problem(
+            vec![
+                recipe("small", Vec::new(), vec![resource(X, 1.0)], 1.0),
+                recipe("large", Vec::new(), vec![resource(X, 1.5)], 1.0),
+            ],
+            vec![resource(X, 2.5)],
+            Vec::new(),
+        );
+        problem.weights = vec![1.0, 1.6];
+        let Ok(integer) = problem.solve_integer() else {
+            panic!("an integer solution exists");
+        };
+        assert!(integer.optimal);
+        let mut counts = counts(&integer.solutions);
+        counts.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            counts,
+            vec![(String::from("large"), 1.0), (String::from("small"), 1.0)]
+        );
+    }
+
+    #[test]
+    fn proves_integer_infeasible() {
+        // 连续解为2.2台，向上取整超出原矿供给，向下取整不满足需求，两个分支都不可行
+        let problem = problem(
+            vec![recipe(
+                "x",
+                vec![resource(ORE, 1.0)],
+                vec![resource(X, 1.0)],
+                1.0,
+            )],
+            vec![resource(X, 2.2)],
+            vec![resource(ORE, 2.5)],
+        );
+        assert!(matches!(
+            problem.solve_integer(),
+            Err(DspCalError::IntegerInfeasible)
+        ));
+    }
+
+    #[test]
+    fn stops_at_node_limit() {
+        let mut problem = problem(
+            vec![recipe(
+                "x",
+                vec![resource(ORE, 1.0)],
+                vec![resource(X, 1.0)],
+                1.0,
+            )],
+            vec![resource(X, 2.2)],
+            vec![resource(ORE, 2.5)],
+        );
+        problem.settings.max_nodes = 1;
+        assert!(matches!(
+            problem.solve_integer(),
+            Err(DspCalError::BranchAndBoundLimit(1))
+        ));
+    }
+
+    #[test]
+    fn reports_limit_when_last_branch_is_cut() {
+        // 连续解为2.5台a，向上取整超出原矿供给，没有初始整数解。
+        // 第2个节点是不可行的向上分支，向下分支留在栈中未求解，不能判定为无整数解
+        let mut problem = problem(
+            vec![
+                recipe("a", vec![resource(ORE, 1.0)], vec![resource(X, 1.0)], 1.0),
+                recipe("b", Vec::new(), vec![resource(X, 1.0)], 1.0),
+            ],
+            vec![resource(X, 2.5)],
+            vec![resource(ORE, 2.5)],
+        );
+        problem.weights = vec![1.0, 2.0];
+        problem.settings.max_nodes = 2;
+        assert!(matches!(
+            problem.solve_integer(),
+            Err(DspCalError::BranchAndBoundLimit(2))
+        ));
+
+        problem.settings.max_nodes = 100;
+        let Ok(integer) = problem.solve_integer() else {
+            panic!("two a and one b satisfy the demand");
+        };
+        assert!(integer.optimal);
+    }
+
+    #[test]
+    fn flags_truncated_search_as_suboptimal() {
+        // 直接向上取整得到3台small作为初始解，根节点分支后达到上限
+        let mut problem = problem(
+            vec![
+                recipe("small", Vec::new(), vec![resource(X, 1.0)], 1.0),
+                recipe("large", Vec::new(), vec![resource(X, 1.5)], 1.0),
+            ],
+            vec![resource(X, 2.5)],
+            Vec::new(),
+        );
+        problem.weights = vec![1.0, 1.6];
+        problem.settings.max_nodes = 1;
+        let Ok(integer) = problem.solve_integer() else {
+            panic!("the ceiled solution is feasible");
+        };
+        assert!(!integer.optimal);
+        assert_eq!(
+            counts(&integer.solutions),
+            vec![(String::from("small"), 3.0)]
+        );
+    }
+
+    #[test]
+    fn modular_reduces_to_smallest_ratio() {
+        // 需要1/3台y和1/2台x，最小整数比例为2台y、3台x，对应1/6个模块
+        let problem = problem(
+            vec![
+                recipe("x", Vec::new(), vec![resource(X, 1.0)], 1.0),
+                recipe("y", vec![resource(X, 3.0)], vec![resource(Y, 1.0)], 2.0),
+            ],
+            vec![resource(Y, 1.0 / 6.0)],
+            Vec::new(),
+        );
+        let Ok(module) = problem.solve_modular() else {
+            panic!("the exact solution exists");
+        };
+        let mut counts: Vec<_> = module
+            .solutions
+            .iter()
+            .map(|solution| (solution.recipe.info.name.clone(), solution.num.clone()))
+            .collect();
+        counts.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            counts,
+            vec![
+                (
+                    String::from("x"),
+                    BigRational::from_integer(BigInt::from(3))
+                ),
+                (
+                    String::from("y"),
+                    BigRational::from_integer(BigInt::from(2))
+                ),
+            ]
+        );
+        assert_eq!(
+            module.count,
+            BigRational::new(BigInt::from(1), BigInt::from(6))
+        );
+    }
+}
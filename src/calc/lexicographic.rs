@@ -0,0 +1,152 @@
+use good_lp::{Expression, SolverModel};
+
+use super::{
+    objective::{maximize_throughput, Stage},
+    translator::from_values,
+    FinalSolve, Problem, ProcessedRecipes, RecipeBinding, Target, THRESHOLD,
+};
+use crate::error::DspCalError;
+
+// 迭代加权的轮数
+const SPARSE_ITERATIONS: usize = 8;
+
+// 迭代加权时加在数量上的小量，避免数量为0的公式权重无穷大
+const SPARSE_EPSILON: f64 = 1e-3;
+
+/// 线性规划的优化方向
+#[derive(Clone, Copy)]
+pub(super) enum Goal {
+    /// 按公式绑定的权重最小化成本
+    MinimizeCost,
+    /// 最大化目标物品的加权产量
+    MaximizeThroughput,
+}
+
+/// 已求解的阶段或分支定界留下的约束
+#[derive(Clone)]
+pub(super) enum Bound {
+    /// 加权成本不超过上限，权重与公式绑定一一对应
+    Cost(Vec<f64>, f64),
+    /// 目标物品的加权产量不低于下限
+    Throughput(f64),
+    /// 第几个公式绑定不再使用
+    Unused(usize),
+    /// 第几个公式绑定的数量不超过上限
+    AtMost(usize, f64),
+    /// 第几个公式绑定的数量不低于下限
+    AtLeast(usize, f64),
+}
+
+/// 把已求解阶段的最优值作为约束加入问题
+pub(super) fn constraint_bounds(
+    processed: &ProcessedRecipes,
+    problem: &mut impl SolverModel,
+    recipe_extra: &[RecipeBinding],
+    targets: &[Target],
+    bounds: &[Bound],
+) {
+    for bound in bounds {
+        let constraint = match bound {
+            Bound::Cost(weights, max) => recipe_extra
+                .iter()
+                .zip(weights)
+                .map(|(recipe, &weight)| weight * recipe.variable)
+                .sum::<Expression>()
+                .leq(*max),
+            Bound::Throughput(min) => maximize_throughput(processed, targets).geq(*min),
+            Bound::Unused(index) => match recipe_extra.get(*index) {
+                Some(recipe) => Expression::from(recipe.variable).leq(0.0),
+                None => continue,
+            },
+            Bound::AtMost(index, max) => match recipe_extra.get(*index) {
+                Some(recipe) => Expression::from(recipe.variable).leq(*max),
+                None => continue,
+            },
+            Bound::AtLeast(index, min) => match recipe_extra.get(*index) {
+                Some(recipe) => Expression::from(recipe.variable).geq(*min),
+                None => continue,
+            },
+        };
+        problem.add_constraint(constraint);
+    }
+}
+
+impl Problem {
+    // 依次求解每个阶段，把前一阶段的最优值在容差内固定为约束
+    pub(super) fn solve_stages(&self) -> Result<FinalSolve, DspCalError> {
+        let relax = |value: f64| self.settings.stage_tolerance * value.abs().max(1.0);
+        let mut bounds = Vec::new();
+
+        // 最大产量模式下，产量是最优先的目标
+        let mut last = if self.targets.is_empty() {
+            None
+        } else {
+            let (recipe_extra, values) =
+                self.solve_values(self.enabled_weights(), Goal::MaximizeThroughput, &bounds)?;
+            let throughput = self
+                .throughput(&from_values(&recipe_extra, &values))
+                .iter()
+                .zip(&self.targets)
+                .map(|(resource, target)| resource.num * target.weight)
+                .sum::<f64>();
+            bounds.push(Bound::Throughput(throughput - relax(throughput)));
+            Some((recipe_extra, values, Goal::MaximizeThroughput, 0))
+        };
+
+        for stage in &self.stages {
+            match stage {
+                Stage::Weights(weights) => {
+                    let raw_ores = self.all_raw_ores();
+                    let weights = self
+                        .enabled_recipes()
+                        .map(|recipe| weights.weight(recipe, &raw_ores))
+                        .collect::<Vec<_>>();
+                    let (recipe_extra, values) =
+                        self.solve_values(weights.clone(), Goal::MinimizeCost, &bounds)?;
+                    let cost = recipe_extra
+                        .iter()
+                        .zip(&values)
+                        .map(|(recipe, value)| recipe.weight * value)
+                        .sum::<f64>();
+                    last = Some((recipe_extra, values, Goal::MinimizeCost, bounds.len()));
+                    bounds.push(Bound::Cost(weights, cost + relax(cost)));
+                }
+                Stage::DistinctRecipes => {
+                    // 从上一阶段的解出发，没有上一阶段时从建筑数量出发
+                    let mut values = match &last {
+                        Some((_, values, _, _)) => values.clone(),
+                        None => vec![1.0 - SPARSE_EPSILON; self.enabled_recipes().count()],
+                    };
+                    for _ in 0..SPARSE_ITERATIONS {
+                        let weights = values
+                            .iter()
+                            .map(|value| 1.0 / (value.max(0.0) + SPARSE_EPSILON))
+                            .collect();
+                        let (recipe_extra, next) =
+                            self.solve_values(weights, Goal::MinimizeCost, &bounds)?;
+                        values.clone_from(&next);
+                        last = Some((recipe_extra, next, Goal::MinimizeCost, bounds.len()));
+                    }
+                    bounds.extend(
+                        values
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, &value)| value <= THRESHOLD)
+                            .map(|(index, _)| Bound::Unused(index)),
+                    );
+                }
+            }
+        }
+
+        // 最后一次求解只受它之前的阶段约束
+        let (recipe_extra, values, goal, len) =
+            last.unwrap_or((Vec::new(), Vec::new(), Goal::MinimizeCost, 0));
+        bounds.truncate(len);
+        Ok(FinalSolve {
+            recipe_extra,
+            values,
+            goal,
+            bounds,
+        })
+    }
+}
@@ -1,31 +1,86 @@
+mod backend;
+mod balance;
+mod belts;
+mod buildings;
 mod config;
 mod constraint;
+mod crossover;
+mod diagnose;
+mod exact;
+mod existing;
+mod imports;
+mod integer;
+mod lexicographic;
 mod objective;
 mod translator;
+mod validate;
+mod verify;
 
 use std::collections::{HashMap, HashSet};
 
-use good_lp::{clarabel, variable, variables, SolverModel, Variable};
+use good_lp::{
+    variable, variables, Expression, ProblemVariables, ResolutionError, SolverModel, Variable,
+};
+
+pub use backend::SolverBackend;
+pub use balance::{balance, Endpoint, Flow, ItemBalance, ItemFlow, Share};
+pub use belts::{belt_report, BeltLoad, RecipeBelts};
+pub use buildings::{building_stats, BuildingStats};
+pub use config::SolverSettings;
+pub use diagnose::Diagnostic;
+pub use exact::{to_rational, ExactSolution};
+pub use existing::Expansion;
+pub use imports::{import_report, Import, ImportKind};
+pub use integer::{IntegerSolution, Module};
+pub use objective::{Objective, ObjectiveWeights, Stage, Target};
+pub use verify::{verify, Deficit};
 
-use config::config_solver;
-use constraint::{constraint_needs, constraint_recipes};
-use objective::minimize_by_weight;
-use translator::from_clarabel_solution;
+#[cfg(feature = "microlp")]
+use backend::Microlp;
+use backend::{Backend, Clarabel};
+use constraint::{constraint_needs, constraint_recipes, constraint_supplies};
+use crossover::crossover;
+use lexicographic::{constraint_bounds, Bound, Goal};
+use objective::{maximize_throughput, minimize_by_weight};
+use translator::{from_values, solution_values};
 
 use crate::{
     dsp::{
         item::{Resource, ResourceType},
-        recipe::Recipe,
+        recipe::{FixedRecipe, Recipe, RecipeBan},
     },
-    error::DspCalError::{self, LpSolverError},
+    error::DspCalError,
 };
 
 pub struct Problem {
     pub recipes: Vec<Recipe>,
     pub needs: Vec<Resource>,
     pub weights: Vec<f64>,
+    /// 视为原矿的资源，全部由外部输入，不参与生产
+    pub raw_ores: Vec<ResourceType>,
+    /// 禁用的公式，不参与求解
+    pub disabled_recipes: Vec<RecipeBan>,
+    /// 原矿的供给上限，单位为每帧，这些资源同样视为原矿
+    pub supplies: Vec<Resource>,
+    /// 以建筑数量给出的需求，求解时这些公式变体的数量不低于给定值，
+    /// 它们的净产物计入需求，见`all_needs`
+    pub fixed_recipes: Vec<FixedRecipe>,
+    /// 已建成的产线，求解时保留这些建筑，只在其上新增建筑满足剩余的需求
+    pub existing_recipes: Vec<FixedRecipe>,
+    /// 最大产量模式的目标，为空时按需求最小化成本
+    pub targets: Vec<Target>,
+    /// 字典序多目标优化的各个阶段，为空时只按权重求解一次
+    pub stages: Vec<Stage>,
+    pub settings: SolverSettings,
 }
 
+// 最大产量模式下成本的权重，只用于在产量相同的解中选出成本最低的
+pub(crate) const TIE_BREAK: f64 = 1e-6;
+
+// 求解器的数值误差，绝对值低于该值的数量、速率、缺口和松弛量都视为0
+pub(crate) const THRESHOLD: f64 = f32::EPSILON as f64;
+
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct Solution {
     pub recipe: Recipe,
     pub num: f64,
@@ -38,6 +93,14 @@ pub struct RecipeBinding {
     pub weight: f64,
 }
 
+/// 最后一次线性规划的输入和结果，精确求解时以它为起点
+struct FinalSolve {
+    recipe_extra: Vec<RecipeBinding>,
+    values: Vec<f64>,
+    goal: Goal,
+    bounds: Vec<Bound>,
+}
+
 struct ProcessedRecipes<'a> {
     consumes: HashMap<ResourceType, Vec<(&'a RecipeBinding, f64)>>,
     produces: HashMap<ResourceType, Vec<(&'a RecipeBinding, f64)>>,
@@ -73,8 +136,9 @@ impl Problem {
     /// 求解的主逻辑
     ///
     /// # Errors
-    /// 有两种情况会返回错误：
+    /// 有三种情况会返回错误：
     /// - 输入的需求列表与权重列表长度不一致
+    /// - 存在无法生产的需求
     /// - 求解失败
     pub fn solve(&self) -> Result<Vec<Solution>, DspCalError> {
         // 验证权重数量
@@ -85,46 +149,286 @@ impl Problem {
             ));
         }
 
-        // 找出所有公式中出现过的产物
-        let productions = Self::find_all_production(&self.recipes);
+        // 检查所有需求都能被生产，避免求解器给出难以理解的错误
+        self.validate()?;
 
+        // 求解并把求解器的内部格式转换成求解器无关的格式
+        let final_solve = self.solve_final()?;
+        let solution = from_values(&final_solve.recipe_extra, &final_solve.values);
+
+        Ok(solution)
+    }
+
+    // 求解到最后一次线性规划，设置了多个阶段时按字典序依次求解
+    fn solve_final(&self) -> Result<FinalSolve, DspCalError> {
+        if !self.stages.is_empty() {
+            return self.solve_stages();
+        }
+        let goal = self.goal();
+        let (recipe_extra, values) = self.solve_values(self.enabled_weights(), goal, &[])?;
+        Ok(FinalSolve {
+            recipe_extra,
+            values,
+            goal,
+            bounds: Vec::new(),
+        })
+    }
+
+    // 用给定的权重绑定公式，在已有约束下求解，返回公式绑定和对应的数量
+    fn solve_values(
+        &self,
+        weights: Vec<f64>,
+        goal: Goal,
+        bounds: &[Bound],
+    ) -> Result<(Vec<RecipeBinding>, Vec<f64>), DspCalError> {
         // 绑定公式、公式权重和线性规划变量，变量即建筑数量
         let mut model = variables!();
-        let recipe_extra = self.bind(&mut model);
+        let recipe_extra = self.bind_weighted(&mut model, weights);
 
-        // 定义优化目标，暂时只支持权重表的形式
-        let objective = minimize_by_weight(&recipe_extra);
+        // 调用求解器进行求解，失败时附带诊断信息
+        let values = self
+            .solve_lp(model, &recipe_extra, goal, bounds, None)
+            .map_err(|error| self.diagnose(error, &recipe_extra, goal, bounds))?;
 
-        // 这个方法就叫minimise，不是minimize，奇异搞笑
-        let mut clarabel_problem = model.minimise(objective).using(clarabel);
+        // 内点法的解可能是多个等价公式的混合，移动到顶点上
+        let values = if self.settings.crossover {
+            crossover(
+                &self.objective_coefficients(&recipe_extra, goal),
+                &self.rows(&recipe_extra, bounds, None),
+                values,
+            )
+        } else {
+            values
+        };
+
+        Ok((recipe_extra, values))
+    }
 
-        // 设置线性规划求解精度
-        config_solver(&mut clarabel_problem);
+    // 有目标物品时最大化产量，否则最小化成本
+    const fn goal(&self) -> Goal {
+        if self.targets.is_empty() {
+            Goal::MinimizeCost
+        } else {
+            Goal::MaximizeThroughput
+        }
+    }
 
+    // 构建并求解线性规划，返回每个公式绑定的数量，`bounds`是已求解阶段留下的约束，
+    // `max_num`不为None时限制每个公式的建筑数量上限
+    fn solve_lp(
+        &self,
+        model: ProblemVariables,
+        recipe_extra: &[RecipeBinding],
+        goal: Goal,
+        bounds: &[Bound],
+        max_num: Option<f64>,
+    ) -> Result<Vec<f64>, ResolutionError> {
+        match self.settings.backend {
+            SolverBackend::Clarabel => {
+                self.solve_lp_with::<Clarabel>(model, recipe_extra, goal, bounds, max_num)
+            }
+            #[cfg(feature = "microlp")]
+            SolverBackend::Microlp => {
+                self.solve_lp_with::<Microlp>(model, recipe_extra, goal, bounds, max_num)
+            }
+        }
+    }
+
+    fn solve_lp_with<B: Backend>(
+        &self,
+        model: ProblemVariables,
+        recipe_extra: &[RecipeBinding],
+        goal: Goal,
+        bounds: &[Bound],
+        max_num: Option<f64>,
+    ) -> Result<Vec<f64>, ResolutionError> {
         // 预处理，构建从产品种类到相关公式的索引，加速约束构建
-        let processed = ProcessedRecipes::new(&recipe_extra);
+        let processed = ProcessedRecipes::new(recipe_extra);
+
+        // 定义优化目标
+        let cost = minimize_by_weight(recipe_extra);
+
+        // 这个方法就叫minimise，不是minimize，奇异搞笑
+        let unsolved = match goal {
+            Goal::MinimizeCost => model.minimise(cost),
+            Goal::MaximizeThroughput => {
+                let throughput = maximize_throughput(&processed, &self.targets);
+                model.maximise(throughput - TIE_BREAK * cost)
+            }
+        };
+
+        // 选择求解器并设置求解精度
+        let mut lp_problem = B::model(unsolved, &self.settings);
 
         // 根据公式生成并设置相应的约束
-        let _ref_constraint = constraint_recipes(&processed, &mut clarabel_problem, &productions);
+        let _ref_constraint =
+            constraint_recipes(&processed, &mut lp_problem, &self.balanced_productions());
 
         // 根据需求列表生成并设置相应的约束
-        let _ref_constraint_need = constraint_needs(&processed, &mut clarabel_problem, &self.needs);
+        let _ref_constraint_need =
+            constraint_needs(&processed, &mut lp_problem, &self.balanced_needs());
 
-        // 调用clarabel进行求解
-        let clarabel_solution = clarabel_problem.solve().map_err(LpSolverError)?;
+        // 根据供给列表生成并设置相应的约束
+        let _ref_constraint_supply =
+            constraint_supplies(&processed, &mut lp_problem, &self.remaining_supplies());
 
-        // 把求解器的内部格式转换成求解器无关的格式
-        let solution = from_clarabel_solution(&recipe_extra, &clarabel_solution);
+        // 已求解阶段的最优值和固定的建筑数量
+        constraint_bounds(
+            &processed,
+            &mut lp_problem,
+            recipe_extra,
+            &self.targets,
+            &[bounds, &self.fixed_bounds(recipe_extra)].concat(),
+        );
 
-        Ok(solution)
+        if let Some(max_num) = max_num {
+            for recipe in recipe_extra {
+                lp_problem.add_constraint(Expression::from(recipe.variable).leq(max_num));
+            }
+        }
+
+        // 调用求解器进行求解，求解器可能把无界问题当作成功，解中出现无穷大
+        let values = solution_values(recipe_extra, &lp_problem.solve()?);
+        if values.iter().all(|value| value.is_finite()) {
+            Ok(values)
+        } else {
+            Err(ResolutionError::Unbounded)
+        }
     }
 
-    // 绑定公式、公式权重和线性规划变量，变量即建筑数量
-    fn bind(&self, model: &mut good_lp::ProblemVariables) -> Vec<RecipeBinding> {
+    // 找出所有公式中出现过的产物，原矿不需要平衡
+    fn balanced_productions(&self) -> Vec<ResourceType> {
+        Self::find_all_production(&self.recipes)
+            .into_iter()
+            .filter(|production| !self.is_raw_ore(*production))
+            .collect()
+    }
+
+    /// 全部需求，即需求列表加上以建筑数量给出的需求的净产物，单位为每帧
+    ///
+    /// 不存在或被禁用的公式变体没有产物，求解前由`validate`报告
+    #[must_use]
+    pub fn all_needs(&self) -> Vec<Resource> {
+        let fixed_outputs = self.fixed_recipes.iter().flat_map(|fixed| {
+            self.enabled_recipes()
+                .find(|recipe| fixed.matches(recipe))
+                .map(|recipe| fixed.outputs(recipe))
+                .unwrap_or_default()
+        });
+        self.needs.iter().copied().chain(fixed_outputs).collect()
+    }
+
+    // 需求中需要生产的部分，原矿需求直接由外部输入
+    fn balanced_needs(&self) -> Vec<Resource> {
+        self.all_needs()
+            .into_iter()
+            .filter(|need| !self.is_raw_ore(need.resource_type))
+            .collect()
+    }
+
+    // 扣除对原矿的直接需求后，剩余可供公式消耗的供给量
+    fn remaining_supplies(&self) -> Vec<Resource> {
+        let needs = self.all_needs();
+        self.supplies
+            .iter()
+            .map(|supply| Resource {
+                resource_type: supply.resource_type,
+                num: supply.num
+                    - needs
+                        .iter()
+                        .filter(|need| need.resource_type == supply.resource_type)
+                        .map(|need| need.num)
+                        .sum::<f64>(),
+            })
+            .collect()
+    }
+
+    /// 计算最大产量模式下各目标物品的净产量，单位为每帧
+    #[must_use]
+    pub fn throughput(&self, solutions: &[Solution]) -> Vec<Resource> {
+        self.targets
+            .iter()
+            .map(|target| Resource {
+                resource_type: target.resource_type,
+                num: solutions
+                    .iter()
+                    .map(|solution| {
+                        let rate = |resources: &[Resource]| {
+                            resources
+                                .iter()
+                                .filter(|resource| resource.resource_type == target.resource_type)
+                                .map(|resource| resource.num)
+                                .sum::<f64>()
+                        };
+                        solution.num
+                            * (rate(&solution.recipe.results) - rate(&solution.recipe.items))
+                            / solution.recipe.time
+                    })
+                    .sum(),
+            })
+            .collect()
+    }
+
+    /// 找出实际生效的禁用项，即至少禁用了一个公式的项
+    #[must_use]
+    pub fn active_bans(&self) -> Vec<&RecipeBan> {
+        self.disabled_recipes
+            .iter()
+            .filter(|ban| self.recipes.iter().any(|recipe| ban.matches(recipe)))
+            .collect()
+    }
+
+    fn is_raw_ore(&self, resource_type: ResourceType) -> bool {
+        self.raw_ores.contains(&resource_type)
+            || self
+                .supplies
+                .iter()
+                .any(|supply| supply.resource_type == resource_type)
+    }
+
+    // 生产原矿的公式和被禁用的公式不参与求解
+    fn is_enabled(&self, recipe: &Recipe) -> bool {
+        !self.disabled_recipes.iter().any(|ban| ban.matches(recipe))
+            && !recipe
+                .results
+                .iter()
+                .any(|result| self.is_raw_ore(result.resource_type))
+    }
+
+    // 所有参与求解的公式
+    fn enabled_recipes(&self) -> impl Iterator<Item = &Recipe> {
+        self.recipes.iter().filter(|recipe| self.is_enabled(recipe))
+    }
+
+    // 参与求解的公式的权重
+    fn enabled_weights(&self) -> Vec<f64> {
         self.recipes
             .iter()
             .zip(self.weights.iter())
-            .map(|(recipe, &weight)| RecipeBinding {
+            .filter(|(recipe, _)| self.is_enabled(recipe))
+            .map(|(_, &weight)| weight)
+            .collect()
+    }
+
+    // 视为原矿的资源，包括有供给上限的资源
+    fn all_raw_ores(&self) -> Vec<ResourceType> {
+        self.raw_ores
+            .iter()
+            .copied()
+            .chain(self.supplies.iter().map(|supply| supply.resource_type))
+            .collect()
+    }
+
+    // 绑定公式、公式权重和线性规划变量，变量即建筑数量
+    fn bind(&self, model: &mut ProblemVariables) -> Vec<RecipeBinding> {
+        self.bind_weighted(model, self.enabled_weights())
+    }
+
+    // 用给定的权重绑定参与求解的公式，权重与`enabled_recipes`一一对应
+    fn bind_weighted(&self, model: &mut ProblemVariables, weights: Vec<f64>) -> Vec<RecipeBinding> {
+        self.enabled_recipes()
+            .zip(weights)
+            .map(|(recipe, weight)| RecipeBinding {
                 recipe: recipe.clone(),
                 variable: model.add(variable().min(0.0)),
                 weight,
@@ -132,6 +436,27 @@ impl Problem {
             .collect::<Vec<_>>()
     }
 
+    // 固定数量的公式变体和已建成的产线
+    fn all_fixed_recipes(&self) -> impl Iterator<Item = &FixedRecipe> {
+        self.fixed_recipes.iter().chain(&self.existing_recipes)
+    }
+
+    // 固定建筑数量的公式变体的数量下限，包括已建成的产线
+    fn fixed_bounds(&self, recipe_extra: &[RecipeBinding]) -> Vec<Bound> {
+        recipe_extra
+            .iter()
+            .enumerate()
+            .filter_map(|(index, recipe)| {
+                let count = self
+                    .all_fixed_recipes()
+                    .filter(|fixed| fixed.matches(&recipe.recipe))
+                    .map(|fixed| fixed.count)
+                    .fold(0.0, |sum, count| sum + count);
+                (count > 0.0).then_some(Bound::AtLeast(index, count))
+            })
+            .collect()
+    }
+
     // 找出所有公式中出现过的产物
     fn find_all_production(recipes: &[Recipe]) -> Vec<ResourceType> {
         recipes
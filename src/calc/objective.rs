@@ -1,6 +1,206 @@
+use std::str::FromStr;
+
 use good_lp::Expression;
+use serde::{Deserialize, Serialize};
+
+use super::{constraint::net_production, ProcessedRecipes, RecipeBinding};
+use crate::{
+    dsp::{building::BuildingType, item::ResourceType, recipe::Recipe},
+    error::DspCalError::{self, InvalidObjectiveWeight, UnknownObjective},
+};
+
+/// 内置的优化目标，可以按名字选择
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, strum_macros::EnumString)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Objective {
+    /// 建筑数量
+    Buildings,
+    /// 建筑功率，单位MJ/帧
+    Power,
+    /// 建筑占地
+    Area,
+    /// 卡顿
+    Lag,
+    /// 原矿消耗量，单位为每帧
+    RawOre,
+}
+
+impl Objective {
+    pub const ALL: [Self; 5] = [
+        Self::Buildings,
+        Self::Power,
+        Self::Area,
+        Self::Lag,
+        Self::RawOre,
+    ];
+
+    /// 单个建筑在该目标下的代价
+    ///
+    /// 原矿包括`raw_ores`中的物品和采矿公式的产物
+    #[must_use]
+    pub fn cost(self, recipe: &Recipe, raw_ores: &[ResourceType]) -> f64 {
+        let building_type = &recipe.info.building_type;
+        match self {
+            Self::Buildings => 1.0,
+            Self::Power => building_type.power(),
+            Self::Area => building_type.area(),
+            Self::Lag => building_type.lag(),
+            Self::RawOre => {
+                let consumed = recipe
+                    .items
+                    .iter()
+                    .filter(|item| raw_ores.contains(&item.resource_type))
+                    .map(|item| item.num)
+                    .sum::<f64>();
+                let mined = match building_type {
+                    BuildingType::矿机 => recipe.results.iter().map(|result| result.num).sum(),
+                    _ => 0.0,
+                };
+                (consumed + mined) / recipe.time
+            }
+        }
+    }
+}
+
+/// 各项优化目标的权重，公式的最终权重是各项的加权和
+///
+/// 可以从`lag`或`lag=1,power=0.5`这样的字符串解析，省略权重时为1
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ObjectiveWeights {
+    /// 卡顿
+    #[serde(default)]
+    pub lag: f64,
+    /// 建筑数量
+    #[serde(default)]
+    pub buildings: f64,
+    /// 建筑功率
+    #[serde(default)]
+    pub power: f64,
+    /// 建筑占地
+    #[serde(default)]
+    pub area: f64,
+    /// 原矿消耗量
+    #[serde(default)]
+    pub raw_ore: f64,
+}
+
+impl Default for ObjectiveWeights {
+    fn default() -> Self {
+        Self {
+            lag: 1.0,
+            ..Self::zero()
+        }
+    }
+}
+
+impl FromStr for ObjectiveWeights {
+    type Err = DspCalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::zero();
+        for term in s.split(',').filter(|term| !term.is_empty()) {
+            let (name, weight) = term.split_once('=').unwrap_or((term, "1"));
+            let objective = name
+                .trim()
+                .parse()
+                .map_err(|_| UnknownObjective(name.to_string()))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| InvalidObjectiveWeight(term.to_string()))?;
+            *weights.get_mut(objective) += weight;
+        }
+        Ok(weights)
+    }
+}
+
+impl ObjectiveWeights {
+    /// 所有权重都为0
+    #[must_use]
+    pub const fn zero() -> Self {
+        Self {
+            lag: 0.0,
+            buildings: 0.0,
+            power: 0.0,
+            area: 0.0,
+            raw_ore: 0.0,
+        }
+    }
+
+    /// 只有一项目标，权重为1
+    #[must_use]
+    pub const fn only(objective: Objective) -> Self {
+        let mut weights = Self::zero();
+        *weights.get_mut(objective) = 1.0;
+        weights
+    }
 
-use super::RecipeBinding;
+    #[must_use]
+    pub const fn get(&self, objective: Objective) -> f64 {
+        match objective {
+            Objective::Buildings => self.buildings,
+            Objective::Power => self.power,
+            Objective::Area => self.area,
+            Objective::Lag => self.lag,
+            Objective::RawOre => self.raw_ore,
+        }
+    }
+
+    pub const fn get_mut(&mut self, objective: Objective) -> &mut f64 {
+        match objective {
+            Objective::Buildings => &mut self.buildings,
+            Objective::Power => &mut self.power,
+            Objective::Area => &mut self.area,
+            Objective::Lag => &mut self.lag,
+            Objective::RawOre => &mut self.raw_ore,
+        }
+    }
+
+    /// 计算单个公式的权重
+    #[must_use]
+    pub fn weight(&self, recipe: &Recipe, raw_ores: &[ResourceType]) -> f64 {
+        Objective::ALL
+            .iter()
+            .map(|&objective| self.get(objective) * objective.cost(recipe, raw_ores))
+            .sum()
+    }
+
+    /// 计算公式列表的权重表
+    #[must_use]
+    pub fn weights(&self, recipes: &[Recipe], raw_ores: &[ResourceType]) -> Vec<f64> {
+        recipes
+            .iter()
+            .map(|recipe| self.weight(recipe, raw_ores))
+            .collect()
+    }
+}
+
+/// 字典序多目标优化中的一个阶段
+///
+/// 可以从`distinct_recipes`或与[`ObjectiveWeights`]相同格式的字符串解析
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// 按权重最小化成本
+    Weights(ObjectiveWeights),
+    /// 最小化使用的公式种类数
+    ///
+    /// 线性规划无法精确表示，用迭代加权的方法近似：上一轮数量越少的公式权重越大，
+    /// 逐步把数量压到0
+    DistinctRecipes,
+}
+
+impl FromStr for Stage {
+    type Err = DspCalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "distinct_recipes" => Ok(Self::DistinctRecipes),
+            s => s.parse().map(Self::Weights),
+        }
+    }
+}
 
 /// 根据传入的权重列表，创建代价表达式
 pub fn minimize_by_weight(recipe: &[RecipeBinding]) -> Expression {
@@ -9,3 +209,19 @@ pub fn minimize_by_weight(recipe: &[RecipeBinding]) -> Expression {
         .map(|recipe| recipe.weight * recipe.variable)
         .sum::<Expression>()
 }
+
+/// 最大产量模式的目标物品
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub resource_type: ResourceType,
+    /// 该物品产量在目标中的权重
+    pub weight: f64,
+}
+
+/// 根据传入的目标列表，创建加权净产量表达式
+pub fn maximize_throughput(processed: &ProcessedRecipes, targets: &[Target]) -> Expression {
+    targets
+        .iter()
+        .map(|target| net_production(processed, target.resource_type) * target.weight)
+        .sum::<Expression>()
+}
@@ -1,18 +1,22 @@
 use crate::calc;
-use good_lp::{solvers::clarabel::ClarabelSolution, Solution};
+use good_lp::Solution;
 
-use super::RecipeBinding;
+use super::{RecipeBinding, THRESHOLD};
 
-// 根据阈值过滤解，并把解转换成求解器无关的格式
-pub fn from_clarabel_solution(
-    recipes: &[RecipeBinding],
-    clarabel_solution: &ClarabelSolution,
-) -> Vec<calc::Solution> {
-    const THRESHOLD: f64 = f32::EPSILON as f64;
+// 读出每个公式绑定的数量，与求解后端无关
+pub fn solution_values(recipes: &[RecipeBinding], solution: &impl Solution) -> Vec<f64> {
     recipes
         .iter()
-        .filter_map(|recipe| {
-            let num = clarabel_solution.value(recipe.variable);
+        .map(|recipe| solution.value(recipe.variable))
+        .collect()
+}
+
+// 根据阈值过滤每个公式绑定的数量，并转换成求解器无关的格式
+pub fn from_values(recipes: &[RecipeBinding], values: &[f64]) -> Vec<calc::Solution> {
+    recipes
+        .iter()
+        .zip(values)
+        .filter_map(|(recipe, &num)| {
             if num > THRESHOLD {
                 Some(calc::Solution {
                     recipe: recipe.recipe.clone(),
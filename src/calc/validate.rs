@@ -0,0 +1,150 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Problem;
+use crate::{
+    dsp::{item::ResourceType, recipe::Recipe},
+    error::DspCalError,
+};
+
+impl Problem {
+    /// 检查所有需求是否都能被生产
+    ///
+    /// 从每个需求出发沿公式图反向搜索。需要平衡的物品如果没有可用的公式生产，
+    /// 或者所有生产公式都需要无法生产的原料，那么它无法生产。
+    /// 这只是可行的必要条件，循环产线是否真的能自给仍由求解器判断。
+    ///
+    /// # Errors
+    /// 固定数量的公式变体或已建成的产线不存在或被禁用时返回错误；
+    /// 按需求的顺序，第一个无法生产的需求没有任何可用的公式生产时返回`UnreachableDemand`，
+    /// 有生产公式但缺少原料时返回`MissingIngredients`，包含断裂的生产链和缺失的原料
+    pub fn validate(&self) -> Result<(), DspCalError> {
+        let recipes: Vec<_> = self
+            .recipes
+            .iter()
+            .filter(|recipe| self.is_enabled(recipe))
+            .collect();
+
+        // 固定数量的公式变体和已建成的产线必须参与求解
+        if let Some(fixed) = self
+            .all_fixed_recipes()
+            .find(|fixed| !recipes.iter().any(|recipe| fixed.matches(recipe)))
+        {
+            return Err(DspCalError::UnknownRecipeVariant(fixed.to_string()));
+        }
+
+        // 从产物到生产公式的索引
+        let mut producers: HashMap<ResourceType, Vec<&Recipe>> = HashMap::new();
+        for recipe in &recipes {
+            for result in &recipe.results {
+                producers
+                    .entry(result.resource_type)
+                    .or_default()
+                    .push(recipe);
+            }
+        }
+
+        let unreachable = self.find_unreachable(&recipes);
+        let is_reachable = |resource_type: &ResourceType| !unreachable.contains(resource_type);
+
+        let Some(demand) = self
+            .all_needs()
+            .iter()
+            .map(|need| need.resource_type)
+            .find(|demand| !is_reachable(demand))
+        else {
+            return Ok(());
+        };
+
+        if producers.contains_key(&demand) {
+            Err(DspCalError::MissingIngredients {
+                demand,
+                chain: Self::broken_chain(demand, &producers, &is_reachable),
+                missing: Self::missing_ingredients(demand, &producers, &is_reachable),
+            })
+        } else {
+            Err(DspCalError::UnreachableDemand(demand))
+        }
+    }
+
+    // 与求解时的约束保持一致：出现在任意公式产物或需求中、且不是原矿的物品需要平衡，
+    // 其余物品没有约束，视为可以任意获取
+    // 先假设所有物品都能生产，再不断标记所有生产公式都缺原料的物品，直到不再变化，
+    // 这样循环产线（例如电力和燃料棒）不会被误判为无法生产
+    fn find_unreachable(&self, recipes: &[&Recipe]) -> HashSet<ResourceType> {
+        let balanced: HashSet<_> = self
+            .recipes
+            .iter()
+            .flat_map(|recipe| recipe.results.iter().map(|result| result.resource_type))
+            .chain(self.all_needs().iter().map(|need| need.resource_type))
+            .filter(|resource_type| !self.is_raw_ore(*resource_type))
+            .collect();
+        let mut unreachable = HashSet::new();
+        loop {
+            let reachable: HashSet<_> = recipes
+                .iter()
+                .filter(|recipe| {
+                    recipe
+                        .items
+                        .iter()
+                        .all(|item| !unreachable.contains(&item.resource_type))
+                })
+                .flat_map(|recipe| recipe.results.iter().map(|result| result.resource_type))
+                .collect();
+            let next: HashSet<_> = balanced.difference(&reachable).copied().collect();
+            if next.len() == unreachable.len() {
+                return unreachable;
+            }
+            unreachable = next;
+        }
+    }
+
+    // 沿第一个生产公式的第一个无法生产的原料向上追溯，得到一条断裂的生产链
+    fn broken_chain(
+        demand: ResourceType,
+        producers: &HashMap<ResourceType, Vec<&Recipe>>,
+        is_reachable: &impl Fn(&ResourceType) -> bool,
+    ) -> Vec<ResourceType> {
+        let mut chain = vec![demand];
+        let mut current = demand;
+        while let Some(next) = producers
+            .get(&current)
+            .and_then(|recipes| recipes.first())
+            .and_then(|recipe| {
+                recipe
+                    .items
+                    .iter()
+                    .map(|item| item.resource_type)
+                    .find(|item| !is_reachable(item) && !chain.contains(item))
+            })
+        {
+            chain.push(next);
+            current = next;
+        }
+        chain
+    }
+
+    // 找出需求上游所有无法生产、且没有任何可用公式生产的原料
+    fn missing_ingredients(
+        demand: ResourceType,
+        producers: &HashMap<ResourceType, Vec<&Recipe>>,
+        is_reachable: &impl Fn(&ResourceType) -> bool,
+    ) -> Vec<ResourceType> {
+        let mut missing = Vec::new();
+        let mut visited = HashSet::from([demand]);
+        let mut stack = vec![demand];
+        while let Some(current) = stack.pop() {
+            match producers.get(&current) {
+                None => missing.push(current),
+                Some(recipes) => {
+                    for item in recipes.iter().flat_map(|recipe| &recipe.items) {
+                        if !is_reachable(&item.resource_type) && visited.insert(item.resource_type)
+                        {
+                            stack.push(item.resource_type);
+                        }
+                    }
+                }
+            }
+        }
+        missing
+    }
+}
@@ -0,0 +1,106 @@
+use std::{collections::HashMap, fmt};
+
+use super::{Problem, Solution, THRESHOLD};
+use crate::dsp::item::{Resource, ResourceType};
+
+/// 未满足的需求，单位为每帧
+#[derive(Clone, Debug)]
+pub struct Deficit {
+    pub resource_type: ResourceType,
+    /// 要求的最低净产量，原矿的供给上限表示为负数
+    pub required: f64,
+    /// 实际的净产量
+    pub net: f64,
+}
+
+impl fmt::Display for Deficit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: required {}, net {}",
+            self.resource_type,
+            self.resource_type.format_rate(self.required, 6),
+            self.resource_type.format_rate(self.net, 6)
+        )
+    }
+}
+
+/// 独立于求解器重新检查物料平衡
+///
+/// 按每个公式的原料、产物和时长重新计算每种资源的净产量。
+/// `needs`中的资源净产量至少为需求量；`balanced_types`中其余的资源净产量至少为0，
+/// 不在其中的资源（原矿、没有公式生产的物品）视为可以任意获取，不检查。
+/// 缺口超过`tolerance`乘以该资源的总流量，且不是求解误差时视为未满足。
+#[must_use]
+pub fn verify(
+    solutions: &[Solution],
+    needs: &[Resource],
+    balanced_types: &[ResourceType],
+    tolerance: f64,
+) -> Vec<Deficit> {
+    // 每种资源的净产量、总流量和下限
+    let mut balances: HashMap<ResourceType, (f64, f64, Option<f64>)> = HashMap::new();
+    for solution in solutions {
+        let scale = solution.num / solution.recipe.time;
+        for item in &solution.recipe.items {
+            let (net, flow, _) = balances.entry(item.resource_type).or_default();
+            let rate = scale * item.num;
+            *net -= rate;
+            *flow += rate;
+        }
+        for result in &solution.recipe.results {
+            let (net, flow, _) = balances.entry(result.resource_type).or_default();
+            let rate = scale * result.num;
+            *net += rate;
+            *flow += rate;
+        }
+    }
+    for need in needs {
+        let (_, flow, required) = balances.entry(need.resource_type).or_default();
+        *flow += need.num.abs();
+        *required = Some(required.unwrap_or(0.0) + need.num);
+    }
+
+    let mut deficits: Vec<_> = balances
+        .into_iter()
+        .filter_map(|(resource_type, (net, flow, required))| {
+            let required = match required {
+                Some(required) => required,
+                None if balanced_types.contains(&resource_type) => 0.0,
+                None => return None,
+            };
+            (required - net > (tolerance * flow).max(THRESHOLD)).then_some(Deficit {
+                resource_type,
+                required,
+                net,
+            })
+        })
+        .collect();
+    deficits.sort_by_cached_key(|deficit| deficit.resource_type.to_string());
+    deficits
+}
+
+impl Problem {
+    /// 用问题的需求和供给上限检查解的物料平衡
+    ///
+    /// 与求解时的约束一致：原矿的直接需求由外部输入，只检查供给上限，
+    /// 只有出现在公式产物中的非原矿物品需要平衡
+    #[must_use]
+    pub fn verify(&self, solutions: &[Solution]) -> Vec<Deficit> {
+        let mut needs = self.balanced_needs();
+        needs.extend(
+            self.remaining_supplies()
+                .into_iter()
+                .map(|supply| Resource {
+                    resource_type: supply.resource_type,
+                    num: -supply.num,
+                }),
+        );
+        verify(
+            solutions,
+            &needs,
+            &self.balanced_productions(),
+            self.settings.verify_tolerance,
+        )
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{error::DspCalError, unit_convert::sec_from_tick};
+
+/// 传送带上物品的最大堆叠层数
+pub const MAX_STACK: u8 = 4;
+
+/// 传送带的等级
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum_macros::EnumString,
+    strum_macros::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum BeltTier {
+    Mk1,
+    Mk2,
+    #[default]
+    Mk3,
+}
+
+impl BeltTier {
+    /// 每秒运送的物品数量，不计堆叠
+    #[must_use]
+    pub const fn speed(&self) -> f64 {
+        match self {
+            Self::Mk1 => 6.0,
+            Self::Mk2 => 12.0,
+            Self::Mk3 => 30.0,
+        }
+    }
+}
+
+/// 传送带设置
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeltSettings {
+    pub tier: BeltTier,
+    /// 物品的堆叠层数，1到`MAX_STACK`
+    pub stack: u8,
+}
+
+impl Default for BeltSettings {
+    fn default() -> Self {
+        Self {
+            tier: BeltTier::default(),
+            stack: 1,
+        }
+    }
+}
+
+impl BeltSettings {
+    /// # Errors
+    /// 堆叠层数不在1到`MAX_STACK`之间时返回错误
+    pub const fn validate(&self) -> Result<(), DspCalError> {
+        if self.stack >= 1 && self.stack <= MAX_STACK {
+            Ok(())
+        } else {
+            Err(DspCalError::InvalidBeltStack(self.stack))
+        }
+    }
+
+    /// 一条传送带每帧运送的物品数量
+    #[must_use]
+    pub fn capacity(&self) -> f64 {
+        sec_from_tick(self.tier.speed() * f64::from(self.stack))
+    }
+}
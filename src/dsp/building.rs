@@ -2,18 +2,40 @@ use dspdb::recipe::RecipeItem;
 
 use crate::unit_convert::sec_from_tick;
 
-#[derive(Clone, Debug)]
+/// 序列化时使用稳定的ASCII标识符，不依赖中文名称
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    strum_macros::Display,
+)]
 pub enum BuildingType {
+    #[serde(rename = "smelter")]
     熔炉 = 1,
+    #[serde(rename = "chemical_plant")]
     化工 = 2,
+    #[serde(rename = "oil_refinery")]
     精炼厂 = 3,
+    #[serde(rename = "assembler")]
     制造台 = 4,
+    #[serde(rename = "particle_collider")]
     对撞机 = 5,
+    #[serde(rename = "fractionator")]
     分馏塔 = 8,
+    #[serde(rename = "matrix_lab")]
     科研站 = 15,
+    #[serde(rename = "mining_machine")]
     矿机,
+    #[serde(rename = "spray_coater")]
     喷涂机,
+    #[serde(rename = "artificial_star")]
     小太阳,
+    #[serde(rename = "ray_receiver")]
     锅盖,
 }
 
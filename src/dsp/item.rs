@@ -1,21 +1,74 @@
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+use std::{convert::Infallible, fmt, str::FromStr};
+
+use dspdb::item::{item_name, ItemData};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::DspCalError,
+    unit_convert::{tick_from_min, tick_from_sec},
+};
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum IndirectResource {
     Energy,
     Area,
 }
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Cargo {
     pub item_id: i16,
     pub level: u8,
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum ResourceType {
     Direct(Cargo),
     Indirect(IndirectResource),
 }
 
-#[derive(Copy, Clone, Debug)]
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Direct(cargo) => write!(
+                f,
+                "{}_{}",
+                item_name(cargo.item_id).unwrap_or_else(|| format!("ItemID{}", cargo.item_id)),
+                cargo.level
+            ),
+            Self::Indirect(IndirectResource::Energy) => write!(f, "电力"),
+            Self::Indirect(IndirectResource::Area) => write!(f, "占地"),
+        }
+    }
+}
+
+impl ResourceType {
+    /// 把每帧的速率换算为显示用的数值：物品为每分钟的数量，电力为MW，占地不变
+    #[must_use]
+    pub const fn amount(self, num: f64) -> f64 {
+        match self {
+            Self::Direct(_) => tick_from_min(num),
+            Self::Indirect(IndirectResource::Energy) => tick_from_sec(num),
+            Self::Indirect(IndirectResource::Area) => num,
+        }
+    }
+
+    /// 给换算后的数值加上单位，例如`60/min`、`12 MW`
+    #[must_use]
+    pub fn with_unit(self, amount: impl fmt::Display) -> String {
+        match self {
+            Self::Direct(_) => format!("{amount}/min"),
+            Self::Indirect(IndirectResource::Energy) => format!("{amount} MW"),
+            Self::Indirect(IndirectResource::Area) => format!("{amount}"),
+        }
+    }
+
+    /// 把每帧的速率格式化为带单位的字符串，`precision`为保留的小数位数
+    #[must_use]
+    pub fn format_rate(self, num: f64, precision: usize) -> String {
+        self.with_unit(format!("{:.precision$}", self.amount(num)))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct Resource {
     pub resource_type: ResourceType,
     pub num: f64,
@@ -46,3 +99,42 @@ impl Resource {
         }
     }
 }
+
+/// 物品的引用方式，可以是物品id，也可以是物品名字
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ItemRef {
+    Id(i16),
+    Name(String),
+}
+
+impl ItemRef {
+    /// 在物品表中查找对应的物品id
+    ///
+    /// # Errors
+    /// 物品表中不存在该物品时返回错误
+    pub fn resolve(&self, items: &[ItemData]) -> Result<i16, DspCalError> {
+        match self {
+            Self::Id(id) => items
+                .iter()
+                .find(|item| item.id == *id)
+                .map(|item| item.id)
+                .ok_or(DspCalError::UnknownItemId(*id)),
+            Self::Name(name) => items
+                .iter()
+                .find(|item| item.name == *name)
+                .map(|item| item.id)
+                .ok_or_else(|| DspCalError::UnknownItemName(name.clone())),
+        }
+    }
+}
+
+impl FromStr for ItemRef {
+    type Err = Infallible;
+
+    // 能解析成数字的视为id，否则视为名字
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse()
+            .map_or_else(|_| Self::Name(s.to_string()), Self::Id))
+    }
+}
@@ -1,3 +1,4 @@
+pub mod belt;
 pub mod building;
 pub mod item;
 pub mod proliferator;
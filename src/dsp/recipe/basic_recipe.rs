@@ -54,6 +54,7 @@ impl Recipe {
 
     fn accelerate(recipe_item: &RecipeItem, items_level: u8) -> Result<Self, DspCalError> {
         let info = RecipeFmtInfo {
+            id: Some(recipe_item.id),
             name: recipe_item.name.clone(),
             proliferator_type: Some(ProliferatorType {
                 level: items_level,
@@ -73,6 +74,7 @@ impl Recipe {
 
     fn productive(recipe_item: &RecipeItem, items_level: u8) -> Result<Self, DspCalError> {
         let info = RecipeFmtInfo {
+            id: Some(recipe_item.id),
             name: recipe_item.name.clone(),
             proliferator_type: Some(ProliferatorType {
                 level: items_level,
@@ -168,6 +170,7 @@ impl Recipe {
         recipe_item: &RecipeItem,
     ) -> Result<(), DspCalError> {
         let info = RecipeFmtInfo {
+            id: Some(recipe_item.id),
             name: recipe_item.name.clone(),
             proliferator_type: Some(ProliferatorType {
                 level: 0,
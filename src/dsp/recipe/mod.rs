@@ -4,28 +4,95 @@ mod photons;
 mod power;
 mod proliferator;
 
+use std::{convert::Infallible, fmt, str::FromStr};
+
 use dspdb::item::ItemData;
 use dspdb::recipe::RecipeItem;
+use serde::{Deserialize, Serialize};
 
-use super::{building::BuildingType, item::Resource};
+use super::{
+    building::BuildingType,
+    item::{Resource, ResourceType},
+};
 use crate::error::DspCalError;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RecipeFmtInfo {
-    pub name: String, // 公式的名字
+    pub id: Option<i64>, // dspdb中的公式id，不是游戏内公式时为None
+    pub name: String,    // 公式的名字
     pub proliferator_type: Option<ProliferatorType>,
     pub building_type: BuildingType, // 生产于什么建筑
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProliferatorType {
     pub level: u8,
     pub is_speed_up: bool,
 }
 
+/// 增产模式
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    strum_macros::EnumString,
+    strum_macros::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ProliferatorMode {
+    /// 无增产
+    #[default]
+    Vanilla,
+    /// 增产
+    Productive,
+    /// 加速
+    Accelerate,
+}
+
+impl ProliferatorType {
+    #[must_use]
+    pub const fn mode(&self) -> ProliferatorMode {
+        if self.level == 0 {
+            ProliferatorMode::Vanilla
+        } else if self.is_speed_up {
+            ProliferatorMode::Accelerate
+        } else {
+            ProliferatorMode::Productive
+        }
+    }
+}
+
+impl RecipeFmtInfo {
+    /// 增产决策的简短描述，例如`增产_4`、`加速_2`、`无增产`，不能喷涂时为`不适用`
+    #[must_use]
+    pub fn decision(&self) -> String {
+        self.proliferator_type.as_ref().map_or_else(
+            || "不适用".to_string(),
+            |t| {
+                if t.level >= 1 {
+                    format!(
+                        "{}_{}",
+                        if t.is_speed_up { "加速" } else { "增产" },
+                        t.level
+                    )
+                } else {
+                    "无增产".to_string()
+                }
+            },
+        )
+    }
+}
+
 impl Default for RecipeFmtInfo {
     fn default() -> Self {
         Self {
+            id: None,
             name: String::from("Unknown Building"),
             proliferator_type: None,
             building_type: BuildingType::矿机, // FIXME 不应该出现未知建筑
@@ -33,7 +100,7 @@ impl Default for RecipeFmtInfo {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Recipe {
     pub items: Vec<Resource>,   // 原料
     pub results: Vec<Resource>, // 产物
@@ -41,7 +108,146 @@ pub struct Recipe {
     pub info: RecipeFmtInfo,    // 不参与计算的信息
 }
 
+/// 公式的引用方式，可以是dspdb中的公式id，也可以是公式名字
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecipeRef {
+    Id(i64),
+    Name(String),
+}
+
+impl RecipeRef {
+    #[must_use]
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        match self {
+            Self::Id(id) => recipe.info.id == Some(*id),
+            Self::Name(name) => recipe.info.name == *name,
+        }
+    }
+}
+
+impl fmt::Display for RecipeRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{id}"),
+            Self::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl FromStr for RecipeRef {
+    type Err = Infallible;
+
+    // 能解析成数字的视为id，否则视为名字
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse()
+            .map_or_else(|_| Self::Name(s.to_string()), Self::Id))
+    }
+}
+
+/// 禁用的公式
+///
+/// `modes`为空时禁用该公式的所有变体，否则只禁用对应增产模式的变体
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipeBan {
+    pub recipe: RecipeRef,
+    #[serde(default)]
+    pub modes: Vec<ProliferatorMode>,
+}
+
+impl RecipeBan {
+    #[must_use]
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        self.recipe.matches(recipe)
+            && (self.modes.is_empty()
+                || recipe
+                    .info
+                    .proliferator_type
+                    .as_ref()
+                    .is_some_and(|t| self.modes.contains(&t.mode())))
+    }
+}
+
+/// 固定建筑数量的公式变体
+///
+/// 由公式、增产模式和喷涂的增产剂等级确定一个变体，无增产时等级为0
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FixedRecipe {
+    pub recipe: RecipeRef,
+    #[serde(default)]
+    pub mode: ProliferatorMode,
+    #[serde(default)]
+    pub level: u8,
+    /// 建筑数量
+    pub count: f64,
+}
+
+impl FixedRecipe {
+    /// 不能喷涂的公式只匹配无增产且等级为0的变体
+    #[must_use]
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        self.recipe.matches(recipe)
+            && recipe.info.proliferator_type.as_ref().map_or(
+                self.mode == ProliferatorMode::Vanilla && self.level == 0,
+                |t| t.mode() == self.mode && t.level == self.level,
+            )
+    }
+
+    /// 这些建筑每帧的净产物，`recipe`是匹配的公式变体
+    ///
+    /// 产物扣除公式消耗的同种物品，不论增产等级，只保留净产量为正的部分，
+    /// 例如X射线裂解消耗2个氢、产出3个氢，净产出只有1个氢
+    #[must_use]
+    pub fn outputs(&self, recipe: &Recipe) -> Vec<Resource> {
+        let same_item = |a: ResourceType, b: ResourceType| match (a, b) {
+            (ResourceType::Direct(a), ResourceType::Direct(b)) => a.item_id == b.item_id,
+            _ => a == b,
+        };
+        recipe
+            .results
+            .iter()
+            .map(|result| {
+                let consumed = recipe
+                    .items
+                    .iter()
+                    .filter(|item| same_item(item.resource_type, result.resource_type))
+                    .fold(0.0, |sum, item| sum + item.num);
+                Resource {
+                    resource_type: result.resource_type,
+                    num: self.count * (result.num - consumed) / recipe.time,
+                }
+            })
+            .filter(|output| output.num > 0.0)
+            .collect()
+    }
+}
+
+impl fmt::Display for FixedRecipe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}@{}", self.recipe, self.mode, self.level)
+    }
+}
+
 impl Recipe {
+    /// 生成所有的公式
+    ///
+    /// # Errors
+    /// 如果配方的建筑类型未定义则返回错误
+    pub fn all_recipes(
+        basic_recipes: &[RecipeItem],
+        items: &[ItemData],
+        cocktail: bool,
+    ) -> Result<Vec<Self>, DspCalError> {
+        Ok([
+            Self::powers(),
+            Self::flatten_recipes(basic_recipes, items, cocktail)?,
+            Self::proliferator_recipes(items, cocktail),
+            Self::mines(items),
+            Self::photons(),
+        ]
+        .concat())
+    }
+
     /// # Errors
     /// 如果配方的建筑类型未定义则返回错误
     pub fn flatten_recipes(
@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use crate::{calc::Diagnostic, dsp::item::ResourceType};
+
 #[derive(Error, Debug)]
 pub enum DspCalError {
     #[error("mismatched recipe weights: recipes count:{0}, weights count:{0}")]
@@ -8,6 +10,54 @@ pub enum DspCalError {
     UnknownBuildingType(i64),
     #[error("lp solver error: {0}")]
     LpSolverError(good_lp::ResolutionError),
+    #[error("lp solver error: {0}\n{1}")]
+    DiagnosedLpSolverError(good_lp::ResolutionError, Diagnostic),
     #[error("unknown item id: {0}")]
     UnknownItemId(i16),
+    #[error("unknown item name: {0}")]
+    UnknownItemName(String),
+    #[error("io error: {0}")]
+    IoError(std::io::Error),
+    #[error("toml parse error: {0}")]
+    TomlParseError(toml::de::Error),
+    #[error("toml write error: {0}")]
+    TomlWriteError(toml::ser::Error),
+    #[error("json error: {0}")]
+    JsonError(serde_json::Error),
+    #[error("csv error: {0}")]
+    CsvError(csv::Error),
+    #[error("xlsx error: {0}")]
+    XlsxError(rust_xlsxwriter::XlsxError),
+    #[error("unknown strategy file format: {0}, expected .toml or .json")]
+    UnknownStrategyFormat(String),
+    #[error("unknown objective: {0}, expected buildings, power, area, lag or raw_ore")]
+    UnknownObjective(String),
+    #[error("invalid objective weight: {0}")]
+    InvalidObjectiveWeight(String),
+    #[error("exact solver error: {0}")]
+    ExactSolverError(String),
+    #[error("no integer solution exists")]
+    IntegerInfeasible,
+    #[error("no integer solution found within {0} branch and bound nodes")]
+    BranchAndBoundLimit(usize),
+    #[error("an objective cannot be combined with lexicographic stages")]
+    ObjectiveWithStages,
+    #[error("invalid belt stack: {0}, expected 1 to 4")]
+    InvalidBeltStack(u8),
+    #[error("unknown or disabled recipe variant: {0}")]
+    UnknownRecipeVariant(String),
+    #[error("unreachable demand: {0}, no enabled recipe produces it")]
+    UnreachableDemand(ResourceType),
+    #[error(
+        "missing ingredients for demand {demand}: {}, missing: {}",
+        chain.iter().map(ToString::to_string).collect::<Vec<_>>().join(" <- "),
+        missing.iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")
+    )]
+    MissingIngredients {
+        demand: ResourceType,
+        /// 断裂的生产链，从需求开始逐级指向无法生产的原料
+        chain: Vec<ResourceType>,
+        /// 导致断裂的原料，没有任何可用的公式生产它们
+        missing: Vec<ResourceType>,
+    },
 }
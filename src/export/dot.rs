@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use dspdb::item::item_name;
+
+use crate::{
+    calc::Solution,
+    dsp::item::{Cargo, Resource, ResourceType},
+};
+
+// 物品节点，合并增产等级时等级统一为0
+const fn item_key(resource_type: ResourceType, collapse_levels: bool) -> ResourceType {
+    match resource_type {
+        ResourceType::Direct(cargo) if collapse_levels => ResourceType::Direct(Cargo {
+            item_id: cargo.item_id,
+            level: 0,
+        }),
+        _ => resource_type,
+    }
+}
+
+fn item_label(resource_type: ResourceType, collapse_levels: bool) -> String {
+    match resource_type {
+        ResourceType::Direct(cargo) if collapse_levels => {
+            item_name(cargo.item_id).unwrap_or_else(|| format!("ItemID{}", cargo.item_id))
+        }
+        _ => resource_type.to_string(),
+    }
+}
+
+// 转义DOT字符串中的引号和反斜杠
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// 把解导出为`Graphviz`的DOT格式
+///
+/// 公式节点标注建筑类型、建筑数量和增产决策，物品节点之间的边标注每分钟的速率，
+/// 需求物品用双线框标出。`collapse_levels`为true时，同一物品的不同增产等级合并为一个节点，
+/// 便于观察增产剂自喷涂之类的循环。
+#[must_use]
+pub fn to_dot(solutions: &[Solution], needs: &[Resource], collapse_levels: bool) -> String {
+    // 物品节点按首次出现的顺序编号，保证输出稳定
+    let mut items: Vec<ResourceType> = Vec::new();
+    let mut item_index: HashMap<ResourceType, usize> = HashMap::new();
+    let mut node = |resource_type: ResourceType| {
+        let key = item_key(resource_type, collapse_levels);
+        *item_index.entry(key).or_insert_with(|| {
+            items.push(key);
+            items.len() - 1
+        })
+    };
+
+    // 边的端点为（公式，物品节点，是否为产物），同一公式与同一物品节点之间的边合并
+    let mut edges: Vec<((usize, usize, bool), ResourceType, f64)> = Vec::new();
+    let mut edge_index: HashMap<(usize, usize, bool), usize> = HashMap::new();
+    for (index, solution) in solutions.iter().enumerate() {
+        let scale = solution.num / solution.recipe.time;
+        let resources = solution
+            .recipe
+            .items
+            .iter()
+            .map(|item| (item, false))
+            .chain(solution.recipe.results.iter().map(|result| (result, true)));
+        for (resource, is_result) in resources {
+            let key = (index, node(resource.resource_type), is_result);
+            let rate = scale * resource.num;
+            if let Some(edge) = edge_index.get(&key).and_then(|&edge| edges.get_mut(edge)) {
+                edge.2 += rate;
+            } else {
+                edge_index.insert(key, edges.len());
+                edges.push((key, resource.resource_type, rate));
+            }
+        }
+    }
+    for need in needs {
+        node(need.resource_type);
+    }
+
+    let recipe_lines = solutions.iter().enumerate().map(|(index, solution)| {
+        let info = &solution.recipe.info;
+        format!(
+            "    r{index} [shape=box, label=\"{}\\n{:?} × {:.4}\\n{}\"];",
+            escape(&info.name),
+            info.building_type,
+            solution.num,
+            info.decision()
+        )
+    });
+    let item_lines = items.iter().enumerate().map(|(index, &resource_type)| {
+        let demand = needs
+            .iter()
+            .filter(|need| item_key(need.resource_type, collapse_levels) == resource_type)
+            .map(|need| need.num)
+            .sum::<f64>();
+        let label = escape(&item_label(resource_type, collapse_levels));
+        if demand > 0.0 {
+            format!(
+                "    i{index} [shape=ellipse, peripheries=2, label=\"{label}\\n需求 {}\"];",
+                resource_type.format_rate(demand, 2)
+            )
+        } else {
+            format!("    i{index} [shape=ellipse, label=\"{label}\"];")
+        }
+    });
+    let edge_lines = edges
+        .iter()
+        .map(|&((recipe, item, is_result), resource_type, rate)| {
+            let (from, to) = if is_result {
+                (format!("r{recipe}"), format!("i{item}"))
+            } else {
+                (format!("i{item}"), format!("r{recipe}"))
+            };
+            format!(
+                "    {from} -> {to} [label=\"{}\"];",
+                resource_type.format_rate(rate, 2)
+            )
+        });
+
+    let body = recipe_lines
+        .chain(item_lines)
+        .chain(edge_lines)
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("digraph production {{\n    rankdir=LR;\n{body}\n}}\n")
+}
@@ -0,0 +1,217 @@
+use good_lp::ResolutionError;
+use serde::Serialize;
+
+use super::{energy, unit};
+use crate::{
+    calc::{building_stats, BuildingStats, Expansion, Import, ImportKind, Problem, Solution},
+    dsp::item::{IndirectResource, Resource, ResourceType},
+    error::DspCalError::{self, DiagnosedLpSolverError, JsonError, LpSolverError},
+};
+
+/// 求解状态
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// 求得最优解
+    Optimal,
+    /// 需求无法满足
+    Infeasible,
+    /// 目标无界
+    Unbounded,
+    /// 其他错误
+    Error,
+}
+
+/// 换算后的速率，物品的单位为每分钟，电力的单位为MW，单位见`unit`
+#[derive(Clone, Debug, Serialize)]
+pub struct Rate {
+    pub resource_type: ResourceType,
+    pub name: String,
+    pub rate: f64,
+    pub unit: &'static str,
+}
+
+impl Rate {
+    fn new(resource_type: ResourceType, rate: f64) -> Self {
+        Self {
+            resource_type,
+            name: resource_type.to_string(),
+            rate: resource_type.amount(rate),
+            unit: unit(resource_type),
+        }
+    }
+}
+
+/// 一个公式的建筑数量和每种原料、产物的速率
+#[derive(Serialize)]
+pub struct RecipeReport<'a> {
+    #[serde(flatten)]
+    pub solution: &'a Solution,
+    /// 已建成和新增的建筑数量
+    #[serde(flatten)]
+    pub expansion: Expansion,
+    pub inputs: Vec<Rate>,
+    pub outputs: Vec<Rate>,
+}
+
+/// 一种资源的物料平衡，单位与`Rate`相同
+#[derive(Clone, Debug, Serialize)]
+pub struct ItemReport {
+    pub resource_type: ResourceType,
+    pub name: String,
+    pub unit: &'static str,
+    pub production: f64,
+    pub consumption: f64,
+    pub demand: f64,
+    pub surplus: f64,
+}
+
+/// 汇总
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Totals {
+    /// 建筑数量的总和
+    pub buildings: f64,
+    /// 每条产线分别向上取整后的建筑数量总和
+    pub buildings_ceil: f64,
+    /// 耗电，单位为MW
+    pub power_consumption: f64,
+    /// 发电，单位为MW
+    pub power_generation: f64,
+}
+
+/// 供其他工具读取的求解结果
+///
+/// 字段名和结构保持稳定，求解失败时`status`不为`optimal`，`error`给出错误信息，其余字段为空
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub recipes: Vec<RecipeReport<'a>>,
+    pub items: Vec<ItemReport>,
+    /// 建筑统计，功率的单位为MW
+    pub buildings: Vec<BuildingStats>,
+    /// 外部输入，速率和自产电力的单位与`Rate`相同
+    pub imports: Vec<Import>,
+    pub totals: Totals,
+}
+
+impl<'a> Report<'a> {
+    /// 由求得的解生成结果
+    #[must_use]
+    pub fn new(problem: &Problem, solutions: &'a [Solution]) -> Self {
+        let rates = |solution: &Solution, resources: &[Resource]| {
+            resources
+                .iter()
+                .map(|resource| {
+                    Rate::new(
+                        resource.resource_type,
+                        solution.num * resource.num / solution.recipe.time,
+                    )
+                })
+                .collect()
+        };
+        let recipes = solutions
+            .iter()
+            .zip(problem.expansions(solutions))
+            .map(|(solution, expansion)| RecipeReport {
+                solution,
+                expansion,
+                inputs: rates(solution, &solution.recipe.items),
+                outputs: rates(solution, &solution.recipe.results),
+            })
+            .collect();
+
+        let items = problem
+            .balance(solutions)
+            .iter()
+            .map(|balance| {
+                let amount = |num| balance.resource_type.amount(num);
+                ItemReport {
+                    resource_type: balance.resource_type,
+                    name: balance.resource_type.to_string(),
+                    unit: unit(balance.resource_type),
+                    production: amount(balance.production),
+                    consumption: amount(balance.consumption),
+                    demand: amount(balance.demand),
+                    surplus: amount(balance.surplus()),
+                }
+            })
+            .collect();
+
+        let mw = |num| ResourceType::Indirect(IndirectResource::Energy).amount(num);
+        let totals = Totals {
+            buildings: solutions.iter().map(|solution| solution.num).sum(),
+            buildings_ceil: solutions.iter().map(|solution| solution.num.ceil()).sum(),
+            power_consumption: mw(solutions
+                .iter()
+                .map(|solution| energy(solution, &solution.recipe.items))
+                .sum()),
+            power_generation: mw(solutions
+                .iter()
+                .map(|solution| energy(solution, &solution.recipe.results))
+                .sum()),
+        };
+
+        Self {
+            status: Status::Optimal,
+            error: None,
+            recipes,
+            items,
+            buildings: building_stats(solutions)
+                .into_iter()
+                .map(|stat| BuildingStats {
+                    power: mw(stat.power),
+                    ..stat
+                })
+                .collect(),
+            imports: problem
+                .import_report(solutions)
+                .into_iter()
+                .map(|import| Import {
+                    rate: import.resource_type.amount(import.rate),
+                    kind: match import.kind {
+                        ImportKind::Power { generated } => ImportKind::Power {
+                            generated: import.resource_type.amount(generated),
+                        },
+                        kind => kind,
+                    },
+                    ..import
+                })
+                .collect(),
+            totals,
+        }
+    }
+
+    /// 由求解失败的错误生成结果
+    #[must_use]
+    pub fn from_error(error: &DspCalError) -> Self {
+        let status = match error {
+            LpSolverError(ResolutionError::Infeasible)
+            | DiagnosedLpSolverError(ResolutionError::Infeasible, _)
+            | DspCalError::UnreachableDemand(_)
+            | DspCalError::MissingIngredients { .. }
+            | DspCalError::IntegerInfeasible => Status::Infeasible,
+            LpSolverError(ResolutionError::Unbounded)
+            | DiagnosedLpSolverError(ResolutionError::Unbounded, _) => Status::Unbounded,
+            _ => Status::Error,
+        };
+        Self {
+            status,
+            error: Some(error.to_string()),
+            recipes: Vec::new(),
+            items: Vec::new(),
+            buildings: Vec::new(),
+            imports: Vec::new(),
+            totals: Totals::default(),
+        }
+    }
+}
+
+/// 把结果序列化为格式化的JSON
+///
+/// # Errors
+/// 序列化失败时返回错误
+pub fn to_json(report: &Report) -> Result<String, DspCalError> {
+    serde_json::to_string_pretty(report).map_err(JsonError)
+}
@@ -0,0 +1,39 @@
+//! 把解导出为其他工具可以读取的格式
+
+pub mod dot;
+pub mod json;
+pub mod sankey;
+pub mod sheet;
+pub mod workbook;
+
+pub use dot::to_dot;
+pub use json::{to_json, Report};
+pub use sankey::to_sankey_svg;
+pub use sheet::{all_sheets, Cell, Sheet};
+pub use workbook::{write_csv, write_csv_dir, write_xlsx};
+
+use crate::{
+    calc::Solution,
+    dsp::item::{IndirectResource, Resource, ResourceType},
+};
+
+// 速率换算后的单位
+const fn unit(resource_type: ResourceType) -> &'static str {
+    match resource_type {
+        ResourceType::Direct(_) => "/min",
+        ResourceType::Indirect(IndirectResource::Energy) => "MW",
+        ResourceType::Indirect(IndirectResource::Area) => "area",
+    }
+}
+
+// 公式消耗或产出的电力，单位为每帧
+fn energy(solution: &Solution, resources: &[Resource]) -> f64 {
+    resources
+        .iter()
+        .filter(|resource| {
+            resource.resource_type == ResourceType::Indirect(IndirectResource::Energy)
+        })
+        .map(|resource| solution.num * resource.num / solution.recipe.time)
+        // 空的和是-0.0，从0.0开始累加避免输出负零
+        .fold(0.0, |sum, rate| sum + rate)
+}
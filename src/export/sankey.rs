@@ -0,0 +1,302 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    calc::{balance, Endpoint, Solution},
+    dsp::{
+        building::BuildingType,
+        item::{Resource, ResourceType},
+    },
+};
+
+// 画布尺寸和间距，单位为像素
+const WIDTH: f64 = 1200.0;
+const HEIGHT: f64 = 800.0;
+const MARGIN: f64 = 40.0;
+const NODE_WIDTH: f64 = 16.0;
+const NODE_GAP: f64 = 12.0;
+
+// 流带的最小宽度，避免速率很小的流带不可见
+const MIN_BAND: f64 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Node {
+    /// 原矿的外部输入
+    Raw(ResourceType),
+    /// 解中第几个公式
+    Recipe(usize),
+    /// 需求物品的输出
+    Demand(ResourceType),
+    /// 剩余的副产物
+    Surplus(ResourceType),
+}
+
+struct Link {
+    from: Node,
+    to: Node,
+    resource_type: ResourceType,
+    rate: f64,
+}
+
+// 节点的位置和大小
+struct Rect {
+    x: f64,
+    y: f64,
+    height: f64,
+}
+
+// 转义XML文本中的特殊字符
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 按物品给流带着色，同一物品颜色相同
+fn color(resource_type: ResourceType) -> String {
+    let hue = match resource_type {
+        ResourceType::Direct(cargo) => i32::from(cargo.item_id) * 47 % 360,
+        ResourceType::Indirect(_) => 0,
+    };
+    format!("hsl({hue},60%,50%)")
+}
+
+/// 把解导出为自包含的SVG桑基图
+///
+/// 原矿和采矿公式在左侧，原矿从左侧流入，经过各个公式，需求物品从右侧流出，剩余的副产物同样在右侧。
+/// 流带宽度与`Solution::num * resource.num / recipe.time`计算的物品速率成正比，
+/// 同一物品的多个来源按比例分给各个去向。电力等间接资源不是物流，不画出。
+#[must_use]
+pub fn to_sankey_svg(solutions: &[Solution], needs: &[Resource]) -> String {
+    let links = item_links(solutions, needs);
+    let layout = Layout::new(&links, solutions);
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" \
+         viewBox=\"0 0 {WIDTH} {HEIGHT}\" font-family=\"sans-serif\" font-size=\"12\">\n{}\n{}\n</svg>\n",
+        layout.render_links(&links).join("\n"),
+        layout.render_nodes(solutions).join("\n")
+    )
+}
+
+// 把每种物品的流动分解为节点之间的流带
+fn item_links(solutions: &[Solution], needs: &[Resource]) -> Vec<Link> {
+    let to_node = |endpoint: Endpoint, resource_type: ResourceType| match endpoint {
+        Endpoint::Recipe(index) => Node::Recipe(index),
+        Endpoint::Import => Node::Raw(resource_type),
+        Endpoint::Demand => Node::Demand(resource_type),
+        Endpoint::Surplus => Node::Surplus(resource_type),
+    };
+    balance(solutions, needs)
+        .iter()
+        .filter(|balance| matches!(balance.resource_type, ResourceType::Direct(_)))
+        .flat_map(|balance| {
+            balance.flows().into_iter().map(move |flow| Link {
+                from: to_node(flow.from, balance.resource_type),
+                to: to_node(flow.to, balance.resource_type),
+                resource_type: balance.resource_type,
+                rate: flow.rate,
+            })
+        })
+        .filter(|link| link.from != link.to)
+        .collect()
+}
+
+// 从需求出发沿原料反向搜索，得到每个公式到需求的距离，与需求无关的公式视为距离1
+fn recipe_depths(links: &[Link]) -> HashMap<usize, usize> {
+    let mut depths: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::new();
+    for link in links {
+        if let (Node::Recipe(index), Node::Demand(_) | Node::Surplus(_)) = (link.from, link.to) {
+            if depths.insert(index, 1).is_none() {
+                queue.push_back(index);
+            }
+        }
+    }
+    while let Some(index) = queue.pop_front() {
+        let next = depths.get(&index).copied().unwrap_or(1) + 1;
+        for link in links {
+            if let (Node::Recipe(producer), Node::Recipe(consumer)) = (link.from, link.to) {
+                if consumer == index && !depths.contains_key(&producer) {
+                    depths.insert(producer, next);
+                    queue.push_back(producer);
+                }
+            }
+        }
+    }
+    depths
+}
+
+// 节点的排布
+struct Layout {
+    nodes: Vec<Node>,
+    rects: HashMap<Node, Rect>,
+    // 每帧速率到像素的比例
+    scale: f64,
+}
+
+impl Layout {
+    #[allow(clippy::cast_precision_loss)] // 列数和每列的节点数都很小
+    fn new(links: &[Link], solutions: &[Solution]) -> Self {
+        // 原矿和采矿公式在最左列，需求和剩余在最右列，其余公式按到需求的距离排在中间
+        let is_mining = |index: usize| {
+            solutions
+                .get(index)
+                .is_some_and(|solution| solution.recipe.info.building_type == BuildingType::矿机)
+        };
+        let depths = recipe_depths(links);
+        let max_depth = depths
+            .iter()
+            .filter(|(&index, _)| !is_mining(index))
+            .map(|(_, &depth)| depth)
+            .max()
+            .unwrap_or(1);
+        let column_of = |node: Node| match node {
+            Node::Raw(_) => 0,
+            Node::Recipe(index) if is_mining(index) => 0,
+            Node::Recipe(index) => max_depth + 1 - depths.get(&index).copied().unwrap_or(1),
+            Node::Demand(_) | Node::Surplus(_) => max_depth + 1,
+        };
+
+        // 节点的大小为流入和流出中较大的一侧
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut inflow: HashMap<Node, f64> = HashMap::new();
+        let mut outflow: HashMap<Node, f64> = HashMap::new();
+        for link in links {
+            for node in [link.from, link.to] {
+                if !nodes.contains(&node) {
+                    nodes.push(node);
+                }
+            }
+            *outflow.entry(link.from).or_default() += link.rate;
+            *inflow.entry(link.to).or_default() += link.rate;
+        }
+        let value = |node: &Node| {
+            let rate = |flows: &HashMap<Node, f64>| flows.get(node).copied().unwrap_or(0.0);
+            rate(&inflow).max(rate(&outflow))
+        };
+
+        // 每列的节点，按流量从大到小排列
+        let mut columns: Vec<Vec<Node>> = vec![Vec::new(); max_depth + 2];
+        for &node in &nodes {
+            if let Some(column) = columns.get_mut(column_of(node)) {
+                column.push(node);
+            }
+        }
+        for column in &mut columns {
+            column.sort_by(|a, b| value(b).total_cmp(&value(a)));
+        }
+
+        // 让最高的一列恰好占满画布
+        let scale = columns
+            .iter()
+            .filter(|column| !column.is_empty())
+            .map(|column| {
+                let total = column.iter().map(value).sum::<f64>();
+                let gaps = NODE_GAP * (column.len() - 1) as f64;
+                (MARGIN.mul_add(-2.0, HEIGHT) - gaps) / total
+            })
+            .filter(|scale| scale.is_finite())
+            .reduce(f64::min)
+            .unwrap_or(1.0);
+
+        let column_step =
+            (MARGIN.mul_add(-2.0, WIDTH) - NODE_WIDTH) / (columns.len() - 1).max(1) as f64;
+        let mut rects: HashMap<Node, Rect> = HashMap::new();
+        for (index, column) in columns.iter().enumerate() {
+            let mut y = MARGIN;
+            for &node in column {
+                let height = (value(&node) * scale).max(MIN_BAND);
+                rects.insert(
+                    node,
+                    Rect {
+                        x: column_step.mul_add(index as f64, MARGIN),
+                        y,
+                        height,
+                    },
+                );
+                y += height + NODE_GAP;
+            }
+        }
+
+        Self {
+            nodes,
+            rects,
+            scale,
+        }
+    }
+
+    // 流带依次占用起点的右侧和终点的左侧
+    fn render_links(&self, links: &[Link]) -> Vec<String> {
+        let mut out_offsets: HashMap<Node, f64> = HashMap::new();
+        let mut in_offsets: HashMap<Node, f64> = HashMap::new();
+        links
+            .iter()
+            .filter_map(|link| {
+                let from = self.rects.get(&link.from)?;
+                let to = self.rects.get(&link.to)?;
+                let band = link.rate * self.scale;
+                let width = band.max(MIN_BAND);
+
+                let out_offset = out_offsets.entry(link.from).or_default();
+                let y0 = from.y + *out_offset + width / 2.0;
+                *out_offset += band;
+                let in_offset = in_offsets.entry(link.to).or_default();
+                let y1 = to.y + *in_offset + width / 2.0;
+                *in_offset += band;
+
+                let x0 = from.x + NODE_WIDTH;
+                let x1 = to.x;
+                let xm = f64::midpoint(x0, x1);
+                Some(format!(
+                    "  <path d=\"M{x0:.2},{y0:.2} C{xm:.2},{y0:.2} {xm:.2},{y1:.2} {x1:.2},{y1:.2}\" \
+                     fill=\"none\" stroke=\"{}\" stroke-opacity=\"0.4\" stroke-width=\"{width:.2}\">\
+                     <title>{}: {}</title></path>",
+                    color(link.resource_type),
+                    escape(&link.resource_type.to_string()),
+                    link.resource_type.format_rate(link.rate, 2)
+                ))
+            })
+            .collect()
+    }
+
+    fn render_nodes(&self, solutions: &[Solution]) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                let rect = self.rects.get(node)?;
+                let label = match node {
+                    Node::Raw(resource_type) => format!("{resource_type}（原矿）"),
+                    Node::Recipe(index) => {
+                        solutions.get(*index).map_or_else(String::new, |solution| {
+                            format!(
+                                "{} {:?}×{:.4} {}",
+                                solution.recipe.info.name,
+                                solution.recipe.info.building_type,
+                                solution.num,
+                                solution.recipe.info.decision()
+                            )
+                        })
+                    }
+                    Node::Demand(resource_type) => format!("{resource_type}（需求）"),
+                    Node::Surplus(resource_type) => format!("{resource_type}（剩余）"),
+                };
+                // 最右一列的文字放在节点左侧
+                let (text_x, anchor) = if matches!(node, Node::Demand(_) | Node::Surplus(_)) {
+                    (rect.x - 4.0, "end")
+                } else {
+                    (rect.x + NODE_WIDTH + 4.0, "start")
+                };
+                Some(format!(
+                    "  <rect x=\"{:.2}\" y=\"{:.2}\" width=\"{NODE_WIDTH}\" height=\"{:.2}\" fill=\"#555\"/>\n  \
+                     <text x=\"{text_x:.2}\" y=\"{:.2}\" text-anchor=\"{anchor}\" \
+                     dominant-baseline=\"middle\">{}</text>",
+                    rect.x,
+                    rect.y,
+                    rect.height,
+                    rect.y + rect.height / 2.0,
+                    escape(&label)
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,240 @@
+use super::energy;
+use crate::{
+    calc::{balance, building_stats, import_report, Flow, ImportKind, Solution},
+    dsp::item::{IndirectResource, Resource, ResourceType},
+    unit_convert::sec_from_tick,
+};
+
+/// 表格中的一个单元格
+#[derive(Clone, Debug)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+}
+
+impl From<String> for Cell {
+    fn from(s: String) -> Self {
+        Self::Text(s)
+    }
+}
+
+impl From<f64> for Cell {
+    fn from(num: f64) -> Self {
+        Self::Number(num)
+    }
+}
+
+/// 导出用的表格，速率的单位为每分钟，功率的单位为MW
+#[derive(Clone, Debug)]
+pub struct Sheet {
+    /// 工作表的名字
+    pub name: &'static str,
+    /// 导出为CSV时的文件名，不含扩展名
+    pub file_stem: &'static str,
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+/// 导出的全部表格：公式、物料平衡、建筑统计、电力、外部输入
+#[must_use]
+pub fn all_sheets(solutions: &[Solution], needs: &[Resource]) -> Vec<Sheet> {
+    vec![
+        recipe_sheet(solutions),
+        balance_sheet(solutions, needs),
+        building_sheet(solutions),
+        power_sheet(solutions),
+        import_sheet(solutions, needs),
+    ]
+}
+
+// 公式的原料或产物，每种资源一行，速率为该公式全部建筑的合计
+fn resource_rows(solution: &Solution, role: &str, resources: &[Resource]) -> Vec<Vec<Cell>> {
+    let info = &solution.recipe.info;
+    resources
+        .iter()
+        .map(|resource| {
+            let rate = solution.num * resource.num / solution.recipe.time;
+            vec![
+                info.name.clone().into(),
+                info.building_type.to_string().into(),
+                info.decision().into(),
+                solution.num.into(),
+                sec_from_tick(solution.recipe.time).into(),
+                String::from(role).into(),
+                resource.resource_type.to_string().into(),
+                resource.resource_type.amount(rate).into(),
+            ]
+        })
+        .collect()
+}
+
+/// 公式表，每行一个公式变体的一种原料或产物
+#[must_use]
+pub fn recipe_sheet(solutions: &[Solution]) -> Sheet {
+    Sheet {
+        name: "公式",
+        file_stem: "recipes",
+        header: vec![
+            "公式",
+            "建筑",
+            "增产决策",
+            "建筑数量",
+            "公式时长（秒）",
+            "类别",
+            "资源",
+            "速率（每分钟或MW）",
+        ],
+        rows: solutions
+            .iter()
+            .flat_map(|solution| {
+                let mut rows = resource_rows(solution, "原料", &solution.recipe.items);
+                rows.extend(resource_rows(solution, "产物", &solution.recipe.results));
+                rows
+            })
+            .collect(),
+    }
+}
+
+/// 物料平衡表，每行一种资源
+#[must_use]
+pub fn balance_sheet(solutions: &[Solution], needs: &[Resource]) -> Sheet {
+    let recipe_names = |flows: &[Flow]| {
+        flows
+            .iter()
+            .filter_map(|flow| solutions.get(flow.solution))
+            .map(|solution| solution.recipe.info.name.clone())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    Sheet {
+        name: "物料平衡",
+        file_stem: "balance",
+        header: vec![
+            "资源",
+            "产量",
+            "消耗量",
+            "需求",
+            "剩余",
+            "生产公式",
+            "消耗公式",
+        ],
+        rows: balance(solutions, needs)
+            .iter()
+            .map(|balance| {
+                let amount = |num| balance.resource_type.amount(num).into();
+                vec![
+                    balance.resource_type.to_string().into(),
+                    amount(balance.production),
+                    amount(balance.consumption),
+                    amount(balance.demand),
+                    amount(balance.surplus()),
+                    recipe_names(&balance.producers).into(),
+                    recipe_names(&balance.consumers).into(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// 建筑统计表，每行一种建筑
+///
+/// 取整后数量是每条产线分别向上取整后的总和，即实际需要摆放的建筑数量
+#[must_use]
+pub fn building_sheet(solutions: &[Solution]) -> Sheet {
+    Sheet {
+        name: "建筑统计",
+        file_stem: "buildings",
+        header: vec![
+            "建筑",
+            "建筑数量",
+            "取整后数量",
+            "功率（MW）",
+            "占地",
+            "卡顿",
+        ],
+        rows: building_stats(solutions)
+            .into_iter()
+            .map(|stat| {
+                vec![
+                    stat.building_type.to_string().into(),
+                    stat.count.into(),
+                    stat.ceil_count.into(),
+                    ResourceType::Indirect(IndirectResource::Energy)
+                        .amount(stat.power)
+                        .into(),
+                    stat.area.into(),
+                    stat.lag.into(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+/// 电力表，每行一个公式的耗电和发电，最后一行为合计
+#[must_use]
+pub fn power_sheet(solutions: &[Solution]) -> Sheet {
+    let mw = |num| ResourceType::Indirect(IndirectResource::Energy).amount(num);
+    let mut rows: Vec<Vec<Cell>> = Vec::new();
+    let (mut consumed, mut produced) = (0.0, 0.0);
+    for solution in solutions {
+        let consumption = energy(solution, &solution.recipe.items);
+        let production = energy(solution, &solution.recipe.results);
+        consumed += consumption;
+        produced += production;
+        rows.push(vec![
+            solution.recipe.info.name.clone().into(),
+            solution.recipe.info.building_type.to_string().into(),
+            solution.num.into(),
+            mw(consumption).into(),
+            mw(production).into(),
+        ]);
+    }
+    rows.push(vec![
+        String::from("合计").into(),
+        String::new().into(),
+        solutions
+            .iter()
+            .map(|solution| solution.num)
+            .sum::<f64>()
+            .into(),
+        mw(consumed).into(),
+        mw(produced).into(),
+    ]);
+    Sheet {
+        name: "电力",
+        file_stem: "power",
+        header: vec!["公式", "建筑", "建筑数量", "耗电（MW）", "发电（MW）"],
+        rows,
+    }
+}
+
+/// 外部输入表，每行一种从外部获得的资源
+#[must_use]
+pub fn import_sheet(solutions: &[Solution], needs: &[Resource]) -> Sheet {
+    Sheet {
+        name: "外部输入",
+        file_stem: "imports",
+        header: vec!["资源", "速率", "来源", "矿机数量", "自产电力（MW）"],
+        rows: import_report(solutions, needs)
+            .into_iter()
+            .map(|import| {
+                let (source, miners, generated) = match import.kind {
+                    ImportKind::Mined { miners } => ("采矿", miners.into(), String::new().into()),
+                    ImportKind::Raw => ("运入", String::new().into(), String::new().into()),
+                    ImportKind::Power { generated } => (
+                        "电力",
+                        String::new().into(),
+                        import.resource_type.amount(generated).into(),
+                    ),
+                };
+                vec![
+                    import.resource_type.to_string().into(),
+                    import.resource_type.amount(import.rate).into(),
+                    String::from(source).into(),
+                    miners,
+                    generated,
+                ]
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,67 @@
+use std::{fs, io, path::Path};
+
+use rust_xlsxwriter::{Format, Workbook};
+
+use super::{Cell, Sheet};
+use crate::error::DspCalError::{self, CsvError, IoError, XlsxError};
+
+/// 把表格写成CSV，包含逗号、引号或换行的单元格会被正确转义
+///
+/// # Errors
+/// 写入失败时返回错误
+pub fn write_csv(sheet: &Sheet, writer: impl io::Write) -> Result<(), DspCalError> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(&sheet.header).map_err(CsvError)?;
+    for row in &sheet.rows {
+        writer
+            .write_record(row.iter().map(|cell| match cell {
+                Cell::Text(text) => text.clone(),
+                Cell::Number(num) => num.to_string(),
+            }))
+            .map_err(CsvError)?;
+    }
+    writer.flush().map_err(IoError)
+}
+
+/// 把每个表格写成目录下的一个CSV文件，目录不存在时创建
+///
+/// # Errors
+/// 创建目录或写入失败时返回错误
+pub fn write_csv_dir(sheets: &[Sheet], dir: &Path) -> Result<(), DspCalError> {
+    fs::create_dir_all(dir).map_err(IoError)?;
+    for sheet in sheets {
+        let file =
+            fs::File::create(dir.join(format!("{}.csv", sheet.file_stem))).map_err(IoError)?;
+        write_csv(sheet, file)?;
+    }
+    Ok(())
+}
+
+/// 把所有表格写成一个xlsx工作簿，每个表格一个工作表
+///
+/// # Errors
+/// 写入失败时返回错误
+pub fn write_xlsx(sheets: &[Sheet], path: &Path) -> Result<(), DspCalError> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+    for sheet in sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sheet.name).map_err(XlsxError)?;
+        for (col, title) in (0..).zip(&sheet.header) {
+            worksheet
+                .write_string_with_format(0, col, *title, &bold)
+                .map_err(XlsxError)?;
+        }
+        for (row, cells) in (1..).zip(&sheet.rows) {
+            for (col, cell) in (0..).zip(cells) {
+                match cell {
+                    Cell::Text(text) => worksheet.write_string(row, col, text),
+                    Cell::Number(num) => worksheet.write_number(row, col, *num),
+                }
+                .map_err(XlsxError)?;
+            }
+        }
+        worksheet.autofit();
+    }
+    workbook.save(path).map_err(XlsxError)
+}
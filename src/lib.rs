@@ -20,4 +20,6 @@
 pub mod calc;
 pub mod dsp;
 pub mod error;
+pub mod export;
+pub mod strategy;
 pub mod unit_convert;
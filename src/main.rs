@@ -1,15 +1,263 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
 use dspcalc::{
-    calc::Problem,
+    calc::{
+        belt_report, building_stats, to_rational, BeltLoad, BuildingStats, Deficit, Endpoint,
+        ExactSolution, Expansion, Import, ImportKind, ItemBalance, Module, ObjectiveWeights,
+        RecipeBelts, Share, Solution, SolverBackend, Stage,
+    },
     dsp::{
-        item::{Resource, ResourceType},
-        recipe::Recipe,
+        belt::{BeltSettings, BeltTier, MAX_STACK},
+        item::{ItemRef, Resource, ResourceType},
+        recipe::{FixedRecipe, ProliferatorMode, Recipe, RecipeBan, RecipeRef},
     },
     error::DspCalError,
-    unit_convert::{min_from_tick, sec_from_tick, tick_from_min, tick_from_sec},
+    export::{
+        all_sheets, sheet::recipe_sheet, to_dot, to_json, to_sankey_svg, write_csv, write_csv_dir,
+        write_xlsx, Report,
+    },
+    strategy::{Demand, Strategy, TargetItem},
+    unit_convert::{sec_from_tick, tick_from_sec, RateUnit},
 };
-use dspdb::item::item_name;
 
-fn print_recipes(solutions: Vec<dspcalc::calc::Solution>) {
+/// 戴森球计划量化计算器
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// 生产策略文件（.toml或.json），命令行参数会追加或覆盖其中的设置
+    #[arg(short, long)]
+    strategy: Option<PathBuf>,
+
+    /// 把最终使用的生产策略保存到文件（.toml或.json）
+    #[arg(long)]
+    save: Option<PathBuf>,
+
+    /// 需求，格式为`物品[@增产等级]=速率[/单位]`，物品可以是id或名字，单位可选s或min，默认为min
+    #[arg(
+        short,
+        long = "need",
+        required_unless_present_any = ["strategy", "targets", "fixed_recipes", "existing_recipes"],
+        value_parser = parse_need
+    )]
+    needs: Vec<Demand>,
+
+    /// 以建筑数量给出的需求，格式为`公式[:增产模式[@增产等级]]=建筑数量`，
+    /// 增产模式默认为vanilla，这些建筑的产物计入需求，可重复
+    #[arg(long = "fixed", value_parser = parse_fixed)]
+    fixed_recipes: Vec<FixedRecipe>,
+
+    /// 已建成的产线，格式与以建筑数量给出的需求相同，求解时保留这些建筑，
+    /// 只在其上新增建筑满足剩余的需求，可重复
+    #[arg(long = "existing", value_parser = parse_fixed)]
+    existing_recipes: Vec<FixedRecipe>,
+
+    /// 原矿的供给上限，格式与需求相同，这些物品同样视为原矿，可重复
+    #[arg(long = "supply", value_parser = parse_need)]
+    supplies: Vec<Demand>,
+
+    /// 最大产量模式的目标物品，格式为`物品[@增产等级][=权重]`，权重默认为1，
+    /// 设置后在供给上限内最大化加权产量，可重复
+    #[arg(long = "target", value_parser = parse_target)]
+    targets: Vec<TargetItem>,
+
+    /// 视为原矿的物品，可以是id或名字，可重复
+    #[arg(long = "raw")]
+    raw_ores: Vec<ItemRef>,
+
+    /// 禁用的公式，格式为`公式[:增产模式,...]`，公式可以是id或名字，
+    /// 增产模式可选vanilla、productive、accelerate，省略时禁用所有变体，可重复
+    #[arg(long = "ban", value_parser = parse_ban)]
+    disabled_recipes: Vec<RecipeBan>,
+
+    /// 不摇匀，即只使用单一增产剂喷涂的物品
+    #[arg(long)]
+    no_cocktail: bool,
+
+    /// 优化目标，格式为`目标[=权重],...`，目标可选buildings、power、area、lag、raw_ore，
+    /// 权重默认为1，多个目标按权重加和，默认使用策略文件中的设置，
+    /// 命令行或策略文件设置了阶段时不可用
+    #[arg(long, conflicts_with = "stages")]
+    objective: Option<ObjectiveWeights>,
+
+    /// 字典序多目标优化的阶段，格式与优化目标相同，或者为distinct_recipes（最小化公式种类数），
+    /// 按给出的顺序依次求解，每个阶段都保持前面阶段的最优值，可重复
+    #[arg(long = "stage")]
+    stages: Vec<Stage>,
+
+    /// 线性规划求解后端，可选clarabel，启用microlp特性时可选microlp，默认使用策略文件中的设置
+    #[arg(long)]
+    backend: Option<SolverBackend>,
+
+    /// 在浮点解的基础上用分数精确求解，输出精确的建筑数量比例，只支持文本输出
+    #[arg(long, conflicts_with_all = ["integer", "modular", "format", "csv_dir", "xlsx"])]
+    exact: bool,
+
+    /// 整数求解，每个公式的建筑数量都取整数
+    #[arg(long, conflicts_with = "modular")]
+    integer: bool,
+
+    /// 输出满足需求的最小整数建筑比例，以及需要的模块数量，只支持文本输出
+    #[arg(long, conflicts_with_all = ["format", "csv_dir", "xlsx"])]
+    modular: bool,
+
+    /// 输出每种物品各来源和去向所占的份额
+    #[arg(long)]
+    shares: bool,
+
+    /// 输出每条产线的原料和产物分别需要的传送带数量
+    #[arg(long)]
+    belts: bool,
+
+    /// 计算传送带数量时使用的传送带，可选mk1、mk2、mk3，默认使用策略文件中的设置
+    #[arg(long)]
+    belt: Option<BeltTier>,
+
+    /// 传送带上物品的堆叠层数，默认使用策略文件中的设置
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=i64::from(MAX_STACK)))]
+    stack: Option<u8>,
+
+    /// 导出DOT格式时，把同一物品的不同增产等级合并为一个节点
+    #[arg(long)]
+    collapse_levels: bool,
+
+    /// 把公式、物料平衡、建筑统计和电力表分别导出为目录下的CSV文件
+    #[arg(long)]
+    csv_dir: Option<PathBuf>,
+
+    /// 把公式、物料平衡、建筑统计和电力表导出为xlsx工作簿
+    #[arg(long)]
+    xlsx: Option<PathBuf>,
+
+    /// 输出格式
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// 便于阅读的文本
+    Text,
+    /// 只输出CSV格式的公式表
+    Csv,
+    /// Graphviz的DOT格式的生产图
+    Dot,
+    /// 自包含的SVG桑基图
+    Svg,
+    /// 供其他工具读取的JSON，求解失败时同样输出JSON
+    Json,
+}
+
+// 解析`物品[@增产等级]`
+fn parse_item_level(s: &str) -> Result<(ItemRef, u8), String> {
+    let (item, level) = match s.split_once('@') {
+        Some((item, level)) => (
+            item,
+            level
+                .parse()
+                .map_err(|e| format!("invalid level `{level}`: {e}"))?,
+        ),
+        None => (s, 0),
+    };
+    Ok((item.parse().map_err(|e| format!("{e:?}"))?, level))
+}
+
+fn parse_need(s: &str) -> Result<Demand, String> {
+    let (item, rate) = s
+        .split_once('=')
+        .ok_or_else(|| format!("missing `=` in need: {s}"))?;
+
+    let (item, level) = parse_item_level(item)?;
+
+    let (rate, unit) = rate.split_once('/').unwrap_or((rate, "min"));
+    let rate = rate
+        .parse()
+        .map_err(|e| format!("invalid rate `{rate}`: {e}"))?;
+    let unit = match unit {
+        "s" => RateUnit::PerSec,
+        "min" => RateUnit::PerMin,
+        _ => return Err(format!("unknown unit `{unit}`, expected `s` or `min`")),
+    };
+
+    Ok(Demand {
+        item,
+        level,
+        rate,
+        unit,
+    })
+}
+
+fn parse_target(s: &str) -> Result<TargetItem, String> {
+    let (item, weight) = match s.split_once('=') {
+        Some((item, weight)) => (
+            item,
+            weight
+                .parse()
+                .map_err(|e| format!("invalid weight `{weight}`: {e}"))?,
+        ),
+        None => (s, 1.0),
+    };
+
+    let (item, level) = parse_item_level(item)?;
+
+    Ok(TargetItem {
+        item,
+        level,
+        weight,
+    })
+}
+
+fn parse_fixed(s: &str) -> Result<FixedRecipe, String> {
+    let (variant, count) = s
+        .split_once('=')
+        .ok_or_else(|| format!("missing `=` in fixed recipe: {s}"))?;
+    let count = count
+        .parse()
+        .map_err(|e| format!("invalid count `{count}`: {e}"))?;
+
+    let (recipe, decision) = variant.split_once(':').unwrap_or((variant, ""));
+    let (mode, level) = match decision.split_once('@') {
+        Some((mode, level)) => (
+            mode,
+            level
+                .parse()
+                .map_err(|e| format!("invalid level `{level}`: {e}"))?,
+        ),
+        None => (decision, 0),
+    };
+    let mode = if mode.is_empty() {
+        ProliferatorMode::Vanilla
+    } else {
+        mode.parse()
+            .map_err(|_| format!("unknown proliferator mode `{mode}`"))?
+    };
+
+    Ok(FixedRecipe {
+        recipe: recipe.parse().map_err(|e| format!("{e:?}"))?,
+        mode,
+        level,
+        count,
+    })
+}
+
+fn parse_ban(s: &str) -> Result<RecipeBan, String> {
+    let (recipe, modes) = s.split_once(':').unwrap_or((s, ""));
+    let modes = modes
+        .split(',')
+        .filter(|mode| !mode.is_empty())
+        .map(|mode| {
+            mode.parse()
+                .map_err(|_| format!("unknown proliferator mode `{mode}`"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(RecipeBan {
+        recipe: recipe.parse().map_err(|e| format!("{e:?}"))?,
+        modes,
+    })
+}
+
+fn print_recipes(solutions: &[Solution]) {
     let recipes_output = solutions
         .iter()
         .map(|solution| format_recipe(solution.num, &solution.recipe))
@@ -19,20 +267,7 @@ fn print_recipes(solutions: Vec<dspcalc::calc::Solution>) {
 }
 
 pub fn format_recipe(num_scale: f64, recipe: &Recipe) -> String {
-    let decision = match &recipe.info.proliferator_type {
-        Some(t) => {
-            if t.level >= 1 {
-                format!(
-                    "{}_{}",
-                    if t.is_speed_up { "加速" } else { "增产" },
-                    t.level
-                )
-            } else {
-                "无增产".to_string()
-            }
-        }
-        None => "不适用".to_string(),
-    };
+    let decision = recipe.info.decision();
 
     let recipe_time = sec_from_tick(recipe.time);
 
@@ -54,80 +289,425 @@ pub fn format_recipe(num_scale: f64, recipe: &Recipe) -> String {
 }
 
 fn format_resources(num_scale: f64, recipe: &Recipe, resource: &Resource) -> String {
-    match resource.resource_type {
-        ResourceType::Direct(cargo) => format!(
-            "{:.6} * {}_{}",
-            tick_from_min(num_scale * resource.num / recipe.time),
-            item_name(cargo.item_id).unwrap_or(format!("ItemID{}", cargo.item_id)),
-            cargo.level
-        ),
-        ResourceType::Indirect(indirect_resource) => match indirect_resource {
-            dspcalc::dsp::item::IndirectResource::Energy => {
+    format_rate(&Resource {
+        resource_type: resource.resource_type,
+        num: num_scale * resource.num / recipe.time,
+    })
+}
+
+// 物品写作`数量 * 物品`，其余资源带单位
+fn format_rate(resource: &Resource) -> String {
+    let resource_type = resource.resource_type;
+    match resource_type {
+        ResourceType::Direct(_) => {
+            format!(
+                "{:.6} * {resource_type}",
+                resource_type.amount(resource.num)
+            )
+        }
+        ResourceType::Indirect(_) => resource_type.format_rate(resource.num, 6),
+    }
+}
+
+fn print_exact_recipes(solutions: &[ExactSolution]) {
+    let recipes_output = solutions
+        .iter()
+        .map(format_exact_recipe)
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("增产决策,建筑数量,公式时长,输入输出\n{recipes_output}");
+}
+
+fn format_exact_recipe(solution: &ExactSolution) -> String {
+    let decision = solution.recipe.info.decision();
+    let recipe_time = sec_from_tick(solution.recipe.time);
+
+    let format_resources = |resources: &[Resource]| {
+        resources
+            .iter()
+            .map(|resource| format_exact_rate(resource.resource_type, &solution.rate(resource)))
+            .collect::<Vec<String>>()
+            .join(" + ")
+    };
+    let items_string = format_resources(&solution.recipe.items);
+    let results_string = format_resources(&solution.recipe.results);
+
+    format!(
+        "{decision},{},{recipe_time:.6?},{items_string} -> {results_string}",
+        solution.num
+    )
+}
+
+// 与`format_rate`相同，换算系数同样是精确的
+fn format_exact_rate(resource_type: ResourceType, rate: &num::BigRational) -> String {
+    let amount = rate * to_rational(resource_type.amount(1.0));
+    match resource_type {
+        ResourceType::Direct(_) => format!("{amount} * {resource_type}"),
+        ResourceType::Indirect(_) => resource_type.with_unit(amount),
+    }
+}
+
+fn print_module(module: &Module) {
+    print_exact_recipes(&module.solutions);
+    print!("模块数量：{}", module.count);
+}
+
+fn print_bans(bans: &[&RecipeBan]) {
+    let bans_output = bans
+        .iter()
+        .map(|ban| {
+            let recipe = match &ban.recipe {
+                RecipeRef::Id(id) => format!("RecipeID{id}"),
+                RecipeRef::Name(name) => name.clone(),
+            };
+            if ban.modes.is_empty() {
+                recipe
+            } else {
+                format!("{recipe}{:?}", ban.modes)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("已禁用公式\n{bans_output}");
+}
+
+fn print_imports(imports: &[Import]) {
+    let imports_output = imports
+        .iter()
+        .map(|import| {
+            let rate = format_amount(import.resource_type, import.rate);
+            match import.kind {
+                ImportKind::Mined { miners } => {
+                    format!("{},{rate},采矿,矿机 {miners:.6} 台", import.resource_type)
+                }
+                ImportKind::Raw => format!("{},{rate},运入,", import.resource_type),
+                ImportKind::Power { generated } => format!(
+                    "{},{rate},电力,自产 {}",
+                    import.resource_type,
+                    format_amount(import.resource_type, generated)
+                ),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("外部输入（每分钟，电力为MW）\n资源,速率,来源,备注\n{imports_output}");
+}
+
+fn print_balances(balances: &[ItemBalance], solutions: &[Solution]) {
+    let recipe_names = |flows: &[dspcalc::calc::Flow]| {
+        flows
+            .iter()
+            .filter_map(|flow| solutions.get(flow.solution))
+            .map(|solution| solution.recipe.info.name.clone())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let balances_output = balances
+        .iter()
+        .map(|balance| {
+            let rate = |num| format_amount(balance.resource_type, num);
+            format!(
+                "{},{},{},{},{},{}",
+                balance.resource_type,
+                rate(balance.production),
+                rate(balance.consumption),
+                rate(balance.surplus()),
+                recipe_names(&balance.producers),
+                recipe_names(&balance.consumers)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("物料平衡（每分钟）\n资源,产量,消耗量,剩余,生产公式,消耗公式\n{balances_output}");
+}
+
+fn print_shares(balances: &[ItemBalance], solutions: &[Solution]) {
+    let endpoint_name = |endpoint: Endpoint| match endpoint {
+        Endpoint::Recipe(index) => solutions
+            .get(index)
+            .map_or_else(String::new, |solution| solution.recipe.info.name.clone()),
+        Endpoint::Import => "外部输入".to_string(),
+        Endpoint::Demand => "需求".to_string(),
+        Endpoint::Surplus => "剩余".to_string(),
+    };
+    let shares_output = balances
+        .iter()
+        .flat_map(|balance| {
+            let line = move |direction: &'static str, share: Share| {
                 format!(
-                    "{:.6} MW",
-                    tick_from_sec(num_scale * resource.num / recipe.time)
+                    "{},{direction},{},{},{:.2}%",
+                    balance.resource_type,
+                    endpoint_name(share.endpoint),
+                    format_amount(balance.resource_type, share.rate),
+                    share.share * 100.0
                 )
-            }
-            dspcalc::dsp::item::IndirectResource::Area => todo!(),
-        },
-    }
+            };
+            let sources = balance
+                .sources()
+                .into_iter()
+                .map(move |share| line("来源", share));
+            let sinks = balance
+                .sinks()
+                .into_iter()
+                .map(move |share| line("去向", share));
+            sources.chain(sinks)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("物料来源与去向（每分钟）\n资源,方向,公式,速率,占比\n{shares_output}");
 }
 
-struct Config {
-    /// 是否摇匀
-    cocktail: bool,
+// 换算后的数值，不带单位
+fn format_amount(resource_type: ResourceType, num: f64) -> String {
+    format!("{:.6}", resource_type.amount(num))
 }
 
-fn main() -> Result<(), DspCalError> {
-    let need_white_cube = Resource::from_item_level(6006, 4, min_from_tick(1125000.0));
-    // let need_proliferator_mk3 = from_item_level(1143, 4, min_from_tick(10000.0));
+fn print_belts(belts: &[RecipeBelts], solutions: &[Solution], settings: &BeltSettings) {
+    let belts_output = belts
+        .iter()
+        .flat_map(|recipe_belts| {
+            let (name, decision) = solutions.get(recipe_belts.solution).map_or_else(
+                || (String::new(), String::new()),
+                |solution| {
+                    (
+                        solution.recipe.info.name.clone(),
+                        solution.recipe.info.decision(),
+                    )
+                },
+            );
+            let line = |direction: &'static str, load: &BeltLoad| {
+                format!(
+                    "{name},{decision},{direction},{},{},{:.6}",
+                    load.resource_type,
+                    format_amount(load.resource_type, load.rate),
+                    load.belts
+                )
+            };
+            recipe_belts
+                .inputs
+                .iter()
+                .map(|load| line("原料", load))
+                .chain(recipe_belts.outputs.iter().map(|load| line("产物", load)))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let total = belts
+        .iter()
+        .fold(0.0, |sum, recipe_belts| sum + recipe_belts.total());
+    let ceil_total = belts
+        .iter()
+        .fold(0.0, |sum, recipe_belts| sum + recipe_belts.ceil_total());
+    println!(
+        "传送带（{}，堆叠{}层）\n公式,增产决策,方向,物品,速率（每分钟）,传送带数量\n{belts_output}\n合计：{total:.6}，每种物品分别取整后：{ceil_total}",
+        settings.tier, settings.stack
+    );
+}
 
-    let raw_recipes = dspdb::recipe::recipes_data();
-    let raw_items = dspdb::item::items_data();
+fn print_building_stats(stats: &[BuildingStats]) {
+    let stats_output = stats
+        .iter()
+        .map(|stat| {
+            format!(
+                "{:?},{:.6},{},{:.6},{:.6},{:.6}",
+                stat.building_type,
+                stat.count,
+                stat.ceil_count,
+                tick_from_sec(stat.power),
+                stat.area,
+                stat.lag
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("建筑统计\n建筑,建筑数量,取整后数量,功率（MW）,占地,卡顿\n{stats_output}");
+}
 
-    // 求解方式，暂时只有是否摇匀
-    let config = Config { cocktail: true };
+fn print_expansions(solutions: &[Solution], expansions: &[Expansion]) {
+    let expansions_output = solutions
+        .iter()
+        .zip(expansions)
+        .map(|(solution, expansion)| {
+            format!(
+                "{},{},{:.6},{:.6},{:.6}",
+                solution.recipe.info.name,
+                solution.recipe.info.decision(),
+                expansion.existing,
+                expansion.added,
+                solution.num
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let (existing, added) = expansions
+        .iter()
+        .fold((0.0, 0.0), |(existing, added), expansion| {
+            (existing + expansion.existing, added + expansion.added)
+        });
+    println!(
+        "已建成与新增建筑\n公式,增产决策,已建成,新增,合计\n{expansions_output}\n合计,,{existing:.6},{added:.6},{:.6}",
+        existing + added
+    );
+}
 
-    // 生成所有的公式
-    let recipes = [
-        Recipe::powers(),
-        Recipe::flatten_recipes(&raw_recipes, &raw_items, config.cocktail)?,
-        Recipe::proliferator_recipes(&raw_items, config.cocktail),
-        Recipe::mines(&raw_items),
-        Recipe::photons(),
-    ]
-    .concat();
+fn print_deficits(deficits: &[Deficit]) {
+    let deficits_output = deficits
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("未满足的需求\n{deficits_output}");
+}
 
-    let weights: Vec<_> = recipes
+fn print_throughput(throughput: &[Resource]) {
+    let throughput_output = throughput
         .iter()
-        .map(|recipe| recipe.info.building_type.lag())
-        .collect();
+        .map(format_rate)
+        .collect::<Vec<_>>()
+        .join("\n");
+    println!("目标产量（每分钟）\n{throughput_output}");
+}
+
+fn main() -> Result<(), DspCalError> {
+    let cli = Cli::parse();
+
+    // 命令行参数追加或覆盖策略文件中的设置
+    let mut strategy = match &cli.strategy {
+        Some(path) => Strategy::load(path)?,
+        None => Strategy::default(),
+    };
+    strategy.needs.extend(cli.needs);
+    strategy.fixed_recipes.extend(cli.fixed_recipes);
+    strategy.existing_recipes.extend(cli.existing_recipes);
+    strategy.supplies.extend(cli.supplies);
+    strategy.targets.extend(cli.targets);
+    strategy.raw_ores.extend(cli.raw_ores);
+    strategy.disabled_recipes.extend(cli.disabled_recipes);
+    if cli.no_cocktail {
+        strategy.cocktail = false;
+    }
+    strategy.stages.extend(cli.stages);
+    if let Some(objective) = cli.objective {
+        // 设置了阶段时目标会被忽略，包括策略文件中的阶段
+        if !strategy.stages.is_empty() {
+            return Err(DspCalError::ObjectiveWithStages);
+        }
+        strategy.objective = objective;
+    }
+    if let Some(backend) = cli.backend {
+        strategy.solver.backend = backend;
+    }
+    if let Some(tier) = cli.belt {
+        strategy.belt.tier = tier;
+    }
+    if let Some(stack) = cli.stack {
+        strategy.belt.stack = stack;
+    }
+    if let Some(path) = &cli.save {
+        strategy.save(path)?;
+    }
 
-    // FIXME 检查并确保所有需求都在配方中
-    // 声明所有需求
-    let needs = vec![need_white_cube];
-    // let needs = vec![need_proliferator_mk3];
+    let raw_recipes = dspdb::recipe::recipes_data();
+    let raw_items = dspdb::item::items_data();
 
     // 创建问题并求解
-    let problem = Problem {
-        recipes,
-        needs,
-        weights,
+    let problem = strategy.to_problem(&raw_recipes, &raw_items)?;
+    if cli.exact {
+        print_exact_recipes(&problem.solve_exact()?);
+        return Ok(());
+    }
+    if cli.modular {
+        print_module(&problem.solve_modular()?);
+        return Ok(());
+    }
+    let result = if cli.integer {
+        problem.solve_integer().map(|integer| {
+            if !integer.optimal {
+                eprintln!(
+                    "警告：分支定界达到{}个节点的上限，整数解可能不是最优的",
+                    problem.settings.max_nodes
+                );
+            }
+            integer.solutions
+        })
+    } else {
+        problem.solve()
     };
-    let solutions = problem.solve()?;
+    if matches!(cli.format, OutputFormat::Json) {
+        let report = match &result {
+            Ok(solutions) => Report::new(&problem, solutions),
+            Err(error) => Report::from_error(error),
+        };
+        println!("{}", to_json(&report)?);
+        return Ok(());
+    }
+    let solutions = result?;
+
+    // 导出
+    if cli.csv_dir.is_some() || cli.xlsx.is_some() {
+        let sheets = all_sheets(&solutions, &problem.all_needs());
+        if let Some(dir) = &cli.csv_dir {
+            write_csv_dir(&sheets, dir)?;
+        }
+        if let Some(path) = &cli.xlsx {
+            write_xlsx(&sheets, path)?;
+        }
+    }
 
     // 输出
-    let price = solutions.iter().map(|a| a.num).sum::<f64>();
-    print_recipes(solutions);
-    print!("总成本：{price}");
+    match cli.format {
+        OutputFormat::Text => {
+            let price = solutions.iter().map(|a| a.num).sum::<f64>();
+            let imports = problem.import_report(&solutions);
+            let throughput = problem.throughput(&solutions);
+            let bans = problem.active_bans();
+            let deficits = problem.verify(&solutions);
+            let balances = problem.balance(&solutions);
+            let stats = building_stats(&solutions);
+            print_recipes(&solutions);
+            print_balances(&balances, &solutions);
+            if cli.shares {
+                print_shares(&balances, &solutions);
+            }
+            if cli.belts {
+                print_belts(
+                    &belt_report(&solutions, &strategy.belt),
+                    &solutions,
+                    &strategy.belt,
+                );
+            }
+            print_building_stats(&stats);
+            if !problem.existing_recipes.is_empty() {
+                print_expansions(&solutions, &problem.expansions(&solutions));
+            }
+            if !throughput.is_empty() {
+                print_throughput(&throughput);
+            }
+            if !bans.is_empty() {
+                print_bans(&bans);
+            }
+            if !imports.is_empty() {
+                print_imports(&imports);
+            }
+            if !deficits.is_empty() {
+                print_deficits(&deficits);
+            }
+            print!("总成本：{price}");
+        }
+        OutputFormat::Csv => write_csv(&recipe_sheet(&solutions), std::io::stdout())?,
+        OutputFormat::Dot => print!(
+            "{}",
+            to_dot(&solutions, &problem.all_needs(), cli.collapse_levels)
+        ),
+        OutputFormat::Svg => print!("{}", to_sankey_svg(&solutions, &problem.all_needs())),
+        OutputFormat::Json => {}
+    }
 
     Ok(())
 }
 
 // FIXME dspdb的一些公式的生产有问题
 // FIXME 重氢，光子，电池：不是原矿，但是有公式生产
-// TODO 接入禁用公式列表（直接移除对应的约束）
-// TODO 增加真正的原矿化（直接移除相关的公式）
 
 // TODO 群友建议
 // 1、原矿化列表显示数量
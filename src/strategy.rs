@@ -0,0 +1,262 @@
+use std::{fs, path::Path};
+
+use dspdb::{item::ItemData, recipe::RecipeItem};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calc::{ObjectiveWeights, Problem, SolverSettings, Stage, Target},
+    dsp::{
+        belt::BeltSettings,
+        item::{Cargo, ItemRef, Resource, ResourceType},
+        recipe::{FixedRecipe, Recipe, RecipeBan},
+    },
+    error::DspCalError::{
+        self, IoError, JsonError, TomlParseError, TomlWriteError, UnknownStrategyFormat,
+    },
+    unit_convert::RateUnit,
+};
+
+/// 一项需求
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Demand {
+    pub item: ItemRef,
+    /// 增产等级
+    #[serde(default)]
+    pub level: u8,
+    pub rate: f64,
+    #[serde(default)]
+    pub unit: RateUnit,
+}
+
+impl Demand {
+    /// # Errors
+    /// 物品表中不存在该物品时返回错误
+    pub fn resolve(&self, items: &[ItemData]) -> Result<Resource, DspCalError> {
+        Ok(Resource::from_item_level(
+            self.item.resolve(items)?,
+            self.level,
+            self.unit.to_tick(self.rate),
+        ))
+    }
+}
+
+/// 最大产量模式的一个目标物品
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TargetItem {
+    pub item: ItemRef,
+    /// 增产等级
+    #[serde(default)]
+    pub level: u8,
+    /// 该物品产量在目标中的权重
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+const fn default_weight() -> f64 {
+    1.0
+}
+
+impl TargetItem {
+    /// # Errors
+    /// 物品表中不存在该物品时返回错误
+    pub fn resolve(&self, items: &[ItemData]) -> Result<Target, DspCalError> {
+        Ok(Target {
+            resource_type: ResourceType::Direct(Cargo {
+                item_id: self.item.resolve(items)?,
+                level: self.level,
+            }),
+            weight: self.weight,
+        })
+    }
+}
+
+/// 生产策略
+///
+/// 包含求解一个问题所需的全部用户输入，可以保存为toml或json文件，在设备和用户之间共享
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Strategy {
+    /// 是否摇匀
+    #[serde(default = "default_cocktail")]
+    pub cocktail: bool,
+    /// 视为原矿的物品
+    #[serde(default)]
+    pub raw_ores: Vec<ItemRef>,
+    /// 禁用的公式
+    #[serde(default)]
+    pub disabled_recipes: Vec<RecipeBan>,
+    #[serde(default)]
+    pub needs: Vec<Demand>,
+    /// 以建筑数量给出的需求，这些建筑的产物计入需求
+    #[serde(default)]
+    pub fixed_recipes: Vec<FixedRecipe>,
+    /// 已建成的产线，求解时保留这些建筑，只在其上新增
+    #[serde(default)]
+    pub existing_recipes: Vec<FixedRecipe>,
+    /// 原矿的供给上限，这些物品同样视为原矿
+    #[serde(default)]
+    pub supplies: Vec<Demand>,
+    /// 最大产量模式的目标物品，非空时在供给上限内最大化加权产量
+    #[serde(default)]
+    pub targets: Vec<TargetItem>,
+    #[serde(default)]
+    pub objective: ObjectiveWeights,
+    /// 字典序多目标优化的各个阶段，非空时依次求解并忽略`objective`
+    #[serde(default)]
+    pub stages: Vec<Stage>,
+    #[serde(default)]
+    pub solver: SolverSettings,
+    /// 计算传送带数量时使用的传送带
+    #[serde(default)]
+    pub belt: BeltSettings,
+}
+
+const fn default_cocktail() -> bool {
+    true
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Self {
+            cocktail: default_cocktail(),
+            raw_ores: Vec::new(),
+            disabled_recipes: Vec::new(),
+            needs: Vec::new(),
+            fixed_recipes: Vec::new(),
+            existing_recipes: Vec::new(),
+            supplies: Vec::new(),
+            targets: Vec::new(),
+            objective: ObjectiveWeights::default(),
+            stages: Vec::new(),
+            solver: SolverSettings::default(),
+            belt: BeltSettings::default(),
+        }
+    }
+}
+
+/// 策略文件的格式，由扩展名决定
+enum Format {
+    Toml,
+    Json,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self, DspCalError> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Ok(Self::Toml),
+            Some("json") => Ok(Self::Json),
+            _ => Err(UnknownStrategyFormat(path.display().to_string())),
+        }
+    }
+}
+
+impl Strategy {
+    /// # Errors
+    /// toml格式错误时返回错误
+    pub fn from_toml(s: &str) -> Result<Self, DspCalError> {
+        toml::from_str(s).map_err(TomlParseError)
+    }
+
+    /// # Errors
+    /// 策略无法表示为toml时返回错误
+    pub fn to_toml(&self) -> Result<String, DspCalError> {
+        toml::to_string_pretty(self).map_err(TomlWriteError)
+    }
+
+    /// # Errors
+    /// json格式错误时返回错误
+    pub fn from_json(s: &str) -> Result<Self, DspCalError> {
+        serde_json::from_str(s).map_err(JsonError)
+    }
+
+    /// # Errors
+    /// 策略无法表示为json时返回错误
+    pub fn to_json(&self) -> Result<String, DspCalError> {
+        serde_json::to_string_pretty(self).map_err(JsonError)
+    }
+
+    /// 从文件读取策略，根据扩展名选择toml或json
+    ///
+    /// # Errors
+    /// 扩展名未知、读取失败或格式错误时返回错误
+    pub fn load(path: &Path) -> Result<Self, DspCalError> {
+        let format = Format::from_path(path)?;
+        let content = fs::read_to_string(path).map_err(IoError)?;
+        match format {
+            Format::Toml => Self::from_toml(&content),
+            Format::Json => Self::from_json(&content),
+        }
+    }
+
+    /// 把策略写入文件，根据扩展名选择toml或json
+    ///
+    /// # Errors
+    /// 扩展名未知、序列化失败或写入失败时返回错误
+    pub fn save(&self, path: &Path) -> Result<(), DspCalError> {
+        let content = match Format::from_path(path)? {
+            Format::Toml => self.to_toml()?,
+            Format::Json => self.to_json()?,
+        };
+        fs::write(path, content).map_err(IoError)
+    }
+
+    /// 根据策略生成公式、需求和权重，构建待求解的问题
+    ///
+    /// # Errors
+    /// 配方的建筑类型未定义，需求中的物品不存在，或传送带的堆叠层数无效时返回错误
+    pub fn to_problem(
+        &self,
+        basic_recipes: &[RecipeItem],
+        items: &[ItemData],
+    ) -> Result<Problem, DspCalError> {
+        self.belt.validate()?;
+        let recipes = Recipe::all_recipes(basic_recipes, items, self.cocktail)?;
+        let needs = self
+            .needs
+            .iter()
+            .map(|need| need.resolve(items))
+            .collect::<Result<Vec<_>, _>>()?;
+        let supplies = self
+            .supplies
+            .iter()
+            .map(|supply| supply.resolve(items))
+            .collect::<Result<Vec<_>, _>>()?;
+        let targets = self
+            .targets
+            .iter()
+            .map(|target| target.resolve(items))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 原矿按未增产的物品输入
+        let raw_ores = self
+            .raw_ores
+            .iter()
+            .map(|raw_ore| {
+                raw_ore
+                    .resolve(items)
+                    .map(|item_id| ResourceType::Direct(Cargo { item_id, level: 0 }))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // 供给上限内的物品同样计入原矿消耗
+        let all_raw_ores: Vec<_> = raw_ores
+            .iter()
+            .copied()
+            .chain(supplies.iter().map(|supply| supply.resource_type))
+            .collect();
+        let weights = self.objective.weights(&recipes, &all_raw_ores);
+
+        Ok(Problem {
+            recipes,
+            needs,
+            weights,
+            raw_ores,
+            disabled_recipes: self.disabled_recipes.clone(),
+            supplies,
+            fixed_recipes: self.fixed_recipes.clone(),
+            existing_recipes: self.existing_recipes.clone(),
+            targets,
+            stages: self.stages.clone(),
+            settings: self.solver.clone(),
+        })
+    }
+}
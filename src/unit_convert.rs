@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// sec -> tick
 #[must_use]
 pub const fn sec_from_tick(tick: f64) -> f64 {
@@ -21,3 +23,33 @@ pub const fn tick_from_sec(sec: f64) -> f64 {
 pub const fn tick_from_min(min: f64) -> f64 {
     min * 3600.0
 }
+
+/// 速率的时间单位
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RateUnit {
+    #[serde(rename = "s")]
+    PerSec,
+    #[default]
+    #[serde(rename = "min")]
+    PerMin,
+}
+
+impl RateUnit {
+    /// 该单位下的速率 -> 每帧的速率
+    #[must_use]
+    pub const fn to_tick(self, rate: f64) -> f64 {
+        match self {
+            Self::PerSec => sec_from_tick(rate),
+            Self::PerMin => min_from_tick(rate),
+        }
+    }
+
+    /// 每帧的速率 -> 该单位下的速率
+    #[must_use]
+    pub const fn from_tick(self, rate: f64) -> f64 {
+        match self {
+            Self::PerSec => tick_from_sec(rate),
+            Self::PerMin => tick_from_min(rate),
+        }
+    }
+}